@@ -0,0 +1,64 @@
+//! Bodies crafted to stress the delimiter matcher. The time per byte must stay the same as
+//! the input grows, e.g. compare the throughput reported for the different sizes.
+
+use bytes::Bytes;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use futures_util::{stream, FutureExt, StreamExt};
+use multipart_rs::{MultipartReader, MultipartType};
+
+const SIZES: [usize; 3] = [1 << 16, 1 << 18, 1 << 20];
+
+/// A document with a single part whose body repeats `pattern` up to `size` bytes
+fn document(pattern: &[u8], size: usize) -> Vec<u8> {
+    let mut data = b"--boundary\r\n\r\n".to_vec();
+    while data.len() < size {
+        data.extend_from_slice(pattern);
+    }
+    data.extend_from_slice(b"\r\n--boundary--\r\n");
+    data
+}
+
+/// Read the document from chunks of `chunk_size` bytes and return the size of the body
+fn read(data: &[u8], chunk_size: usize) -> usize {
+    let chunks: Vec<Result<Bytes, std::io::Error>> = data
+        .chunks(chunk_size)
+        .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+        .collect();
+    let reader = MultipartReader::from_stream_with_boundary_and_type(
+        stream::iter(chunks),
+        "boundary",
+        MultipartType::Mixed,
+    )
+    .unwrap();
+
+    reader
+        .map(|item| item.unwrap().data.len())
+        .collect::<Vec<_>>()
+        .now_or_never()
+        .unwrap()
+        .iter()
+        .sum()
+}
+
+fn bench(c: &mut Criterion, name: &str, pattern: &[u8], chunk_size: usize) {
+    let mut group = c.benchmark_group(name);
+    for size in SIZES {
+        let data = document(pattern, size);
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &data, |b, data| {
+            b.iter(|| read(data, chunk_size))
+        });
+    }
+    group.finish();
+}
+
+fn adversarial(c: &mut Criterion) {
+    bench(c, "near_delimiters", b"\r\n--boundarX", 8192);
+    bench(c, "near_delimiters_split", b"\r\n--boundarX", 7);
+    bench(c, "empty_lines", b"\r\n", 8192);
+    bench(c, "dashes", b"-", 8192);
+    bench(c, "carriage_returns", b"\r", 8192);
+}
+
+criterion_group!(benches, adversarial);
+criterion_main!(benches);
@@ -0,0 +1,100 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use actix_http::body::{BodySize, MessageBody};
+use bytes::Bytes;
+
+use crate::{error::MultipartError, writer::MultipartWriter};
+
+/// A writer as the body of an actix-web response, closing the document once all parts were sent.
+///
+/// Bodies of part sources are read on the thread polling the response, so blocking sources
+/// like files should be kept small or appended as in-memory bodies.
+pub struct MultipartBody {
+    writer: MultipartWriter,
+    closed: bool,
+}
+
+impl MultipartBody {
+    /// The Content-Type header value of the response
+    pub fn content_type(&self) -> String {
+        self.writer.content_type()
+    }
+}
+
+impl MultipartWriter {
+    /// Turn the writer into a response body, e.g.
+    ///
+    /// ```ignore
+    /// HttpResponse::Ok()
+    ///     .content_type(writer.content_type())
+    ///     .body(writer.into_body())
+    /// ```
+    pub fn into_body(self) -> MultipartBody {
+        MultipartBody {
+            writer: self,
+            closed: false,
+        }
+    }
+}
+
+impl MessageBody for MultipartBody {
+    type Error = MultipartError;
+
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let this = self.get_mut();
+        loop {
+            match this.writer.take_chunk() {
+                Ok(Some(chunk)) => return Poll::Ready(Some(Ok(chunk))),
+                Ok(None) if !this.closed => {
+                    this.writer.close();
+                    this.closed = true;
+                }
+                Ok(None) => return Poll::Ready(None),
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multipart_type::MultipartType;
+
+    #[futures_test::test]
+    async fn streams_document() {
+        let mut writer =
+            MultipartWriter::new_with_boundary("boundary", MultipartType::Mixed).unwrap();
+        writer
+            .append(
+                &[("Content-Type".to_string(), "text/plain".to_string())],
+                "first",
+            )
+            .unwrap();
+        writer.append_reader(&[], &b"second"[..]).unwrap();
+
+        let body = writer.into_body();
+        assert_eq!(body.content_type(), "multipart/mixed; boundary=boundary");
+        assert_eq!(
+            actix_http::body::to_bytes(body).await.unwrap(),
+            &b"--boundary\r
+Content-Type: text/plain\r
+\r
+first\r
+--boundary\r
+\r
+second\r
+--boundary--\r\n"[..]
+        );
+    }
+}
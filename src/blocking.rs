@@ -0,0 +1,66 @@
+use futures_util::StreamExt;
+
+use crate::{
+    error::MultipartError,
+    reader::{MultipartItem, MultipartReader},
+};
+
+/// Reads the parts of an async reader through a blocking `Iterator`.
+///
+/// Each call to `next` drives the reader on the current thread until the next part is
+/// complete, which suits synchronous code such as `spawn_blocking` sections. The thread is
+/// blocked while the source is pending, so never use the bridge from within an async task.
+pub struct BlockingBridge<'a, E> {
+    reader: MultipartReader<'a, E>,
+}
+
+impl<'a, E> BlockingBridge<'a, E> {
+    pub fn new(reader: MultipartReader<'a, E>) -> BlockingBridge<'a, E> {
+        BlockingBridge { reader }
+    }
+
+    pub fn get_ref(&self) -> &MultipartReader<'a, E> {
+        &self.reader
+    }
+
+    pub fn into_inner(self) -> MultipartReader<'a, E> {
+        self.reader
+    }
+}
+
+impl<'a, E> Iterator for BlockingBridge<'a, E> {
+    type Item = Result<MultipartItem, MultipartError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        futures_executor::block_on(self.reader.next())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multipart_type::MultipartType;
+
+    #[test]
+    fn iterates_parts_blocking() {
+        let data = b"--boundary\r
+Content-Type: text/plain\r
+\r
+first\r
+--boundary\r
+\r
+second\r
+--boundary--\r\n";
+        let reader = MultipartReader::<std::io::Error>::from_data_with_boundary_and_type(
+            data,
+            "boundary",
+            MultipartType::Mixed,
+        )
+        .unwrap();
+
+        let bodies: Vec<_> = BlockingBridge::new(reader)
+            .map(|item| item.unwrap().data)
+            .collect();
+        assert_eq!(bodies, vec![&b"first"[..], &b"second"[..]]);
+    }
+}
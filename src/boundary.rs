@@ -0,0 +1,262 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+};
+
+/// Generates the boundaries used by the writer.
+///
+/// Implement this to use a different entropy source or format; the generated boundary must
+/// consist of 1 to 70 characters allowed by RFC 2046.
+pub trait BoundaryStrategy {
+    fn generate(&mut self) -> String;
+}
+
+/// Source of the randomness used for boundaries.
+///
+/// Inject a different source into the strategies to control the generated boundaries, e.g.
+/// `SeededRandom` in tests or a CSPRNG where boundaries must be unpredictable.
+pub trait RandomSource {
+    fn next_u64(&mut self) -> u64;
+}
+
+/// Randomness from the randomly seeded std hasher, the default
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsRandom;
+
+/// Deterministic pseudo-random numbers from a seed (splitmix64).
+///
+/// Not suitable when the boundary must not be predictable.
+#[derive(Debug, Clone)]
+pub struct SeededRandom {
+    state: u64,
+}
+
+/// Random hexadecimal boundary, the default
+#[derive(Debug, Clone)]
+pub struct RandomHex<R = OsRandom> {
+    len: usize,
+    random: R,
+}
+
+/// Random boundary formatted like a version 4 UUID
+#[derive(Debug, Clone)]
+pub struct UuidBoundary<R = OsRandom> {
+    random: R,
+}
+
+/// Random boundary of URL-safe characters, like nanoid
+#[derive(Debug, Clone)]
+pub struct NanoId<R = OsRandom> {
+    len: usize,
+    random: R,
+}
+
+/// Deterministic boundaries from a seed, for reproducible output (e.g. in tests).
+///
+/// Not suitable when the boundary must not be predictable.
+#[derive(Debug, Clone)]
+pub struct Seeded {
+    random: SeededRandom,
+}
+
+const NANOID_ALPHABET: &[u8] = b"_-0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+impl RandomSource for OsRandom {
+    fn next_u64(&mut self) -> u64 {
+        RandomState::new().build_hasher().finish()
+    }
+}
+
+impl SeededRandom {
+    pub fn new(seed: u64) -> SeededRandom {
+        SeededRandom { state: seed }
+    }
+}
+
+impl RandomSource for SeededRandom {
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+}
+
+impl<R: RandomSource + ?Sized> RandomSource for &mut R {
+    fn next_u64(&mut self) -> u64 {
+        (**self).next_u64()
+    }
+}
+
+impl RandomHex {
+    pub fn new(len: usize) -> RandomHex {
+        RandomHex::with_random(len, OsRandom)
+    }
+}
+
+impl<R: RandomSource> RandomHex<R> {
+    pub fn with_random(len: usize, random: R) -> RandomHex<R> {
+        RandomHex {
+            len: len.clamp(1, 70),
+            random,
+        }
+    }
+}
+
+impl Default for RandomHex {
+    fn default() -> Self {
+        RandomHex::new(32)
+    }
+}
+
+impl Default for UuidBoundary {
+    fn default() -> Self {
+        UuidBoundary::with_random(OsRandom)
+    }
+}
+
+impl<R: RandomSource> UuidBoundary<R> {
+    pub fn with_random(random: R) -> UuidBoundary<R> {
+        UuidBoundary { random }
+    }
+}
+
+impl NanoId {
+    pub fn new(len: usize) -> NanoId {
+        NanoId::with_random(len, OsRandom)
+    }
+}
+
+impl<R: RandomSource> NanoId<R> {
+    pub fn with_random(len: usize, random: R) -> NanoId<R> {
+        NanoId {
+            len: len.clamp(1, 70),
+            random,
+        }
+    }
+}
+
+impl Default for NanoId {
+    fn default() -> Self {
+        NanoId::new(21)
+    }
+}
+
+impl Seeded {
+    pub fn new(seed: u64) -> Seeded {
+        Seeded {
+            random: SeededRandom::new(seed),
+        }
+    }
+}
+
+impl<R: RandomSource> BoundaryStrategy for RandomHex<R> {
+    fn generate(&mut self) -> String {
+        hex(self.len, &mut self.random)
+    }
+}
+
+impl<R: RandomSource> BoundaryStrategy for UuidBoundary<R> {
+    fn generate(&mut self) -> String {
+        let mut bytes = [0u8; 16];
+        bytes[..8].copy_from_slice(&self.random.next_u64().to_be_bytes());
+        bytes[8..].copy_from_slice(&self.random.next_u64().to_be_bytes());
+
+        // Version 4, variant 1
+        bytes[6] = (bytes[6] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+        let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        format!(
+            "{}-{}-{}-{}-{}",
+            &hex[..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..]
+        )
+    }
+}
+
+impl<R: RandomSource> BoundaryStrategy for NanoId<R> {
+    fn generate(&mut self) -> String {
+        // 64 characters, so every 6 bits pick one without bias
+        let mut boundary = String::with_capacity(self.len);
+        while boundary.len() < self.len {
+            let mut bits = self.random.next_u64();
+            for _ in 0..10 {
+                if boundary.len() == self.len {
+                    break;
+                }
+                boundary.push(NANOID_ALPHABET[(bits & 63) as usize] as char);
+                bits >>= 6;
+            }
+        }
+        boundary
+    }
+}
+
+impl BoundaryStrategy for Seeded {
+    fn generate(&mut self) -> String {
+        hex(32, &mut self.random)
+    }
+}
+
+fn hex(len: usize, random: &mut impl RandomSource) -> String {
+    let mut boundary = String::with_capacity(len + 16);
+    while boundary.len() < len {
+        boundary.push_str(&format!("{:016x}", random.next_u64()));
+    }
+    boundary.truncate(len);
+    boundary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{multipart_type::MultipartType, writer::MultipartWriter};
+
+    #[test]
+    fn generates_valid_boundaries() {
+        let mut strategies: Vec<Box<dyn BoundaryStrategy>> = vec![
+            Box::new(RandomHex::default()),
+            Box::new(UuidBoundary::default()),
+            Box::new(NanoId::new(70)),
+            Box::new(Seeded::new(42)),
+        ];
+
+        for strategy in strategies.iter_mut() {
+            let writer =
+                MultipartWriter::new_with_strategy(MultipartType::Mixed, strategy.as_mut())
+                    .unwrap();
+            assert_ne!(writer.boundary, strategy.generate());
+        }
+
+        assert_eq!(UuidBoundary::default().generate().len(), 36);
+        assert_eq!(NanoId::new(70).generate().len(), 70);
+    }
+
+    #[test]
+    fn seeded_is_deterministic() {
+        assert_eq!(Seeded::new(7).generate(), Seeded::new(7).generate());
+        assert_ne!(Seeded::new(7).generate(), Seeded::new(8).generate());
+    }
+
+    #[test]
+    fn strategies_use_injected_random() {
+        let mut random = SeededRandom::new(7);
+        assert_eq!(
+            RandomHex::with_random(32, &mut random).generate(),
+            Seeded::new(7).generate()
+        );
+
+        let uuid = |seed| UuidBoundary::with_random(SeededRandom::new(seed)).generate();
+        assert_eq!(uuid(1), uuid(1));
+        assert_ne!(uuid(1), uuid(2));
+        assert_eq!(
+            NanoId::with_random(21, SeededRandom::new(3)).generate(),
+            NanoId::with_random(21, SeededRandom::new(3)).generate()
+        );
+    }
+}
@@ -0,0 +1,231 @@
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use bytes::{BufMut, Bytes};
+use futures_core::Stream;
+
+use crate::{
+    error::MultipartError,
+    multipart_type::MultipartType,
+    writer::{self, MultipartWriter},
+};
+
+/// Server side of a `multipart/x-mixed-replace` stream (e.g. MJPEG) with many subscribers.
+///
+/// Every part is encoded once and shared between all subscribers. A subscriber receives the
+/// boundary-framed parts sent after it subscribed, so it never sees a partial part. Each
+/// subscriber has its own bounded queue: when it doesn't keep up, its oldest queued part is
+/// dropped for the newest one, so it skips ahead to the latest part instead of holding up
+/// the others.
+pub struct MultipartBroadcaster {
+    pub boundary: String,
+    /// Number of parts queued per subscriber before its oldest ones are dropped
+    capacity: usize,
+    subscribers: Mutex<Vec<Arc<Mutex<Queue>>>>,
+}
+
+/// A stream of boundary-framed parts received from a `MultipartBroadcaster`
+pub struct MultipartSubscriber {
+    queue: Arc<Mutex<Queue>>,
+}
+
+/// Parts queued for a subscriber
+#[derive(Default)]
+struct Queue {
+    frames: VecDeque<Bytes>,
+    /// Whether the closing delimiter was queued, after which the stream ends
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+impl MultipartBroadcaster {
+    /// Create a broadcaster queueing up to `capacity` parts per subscriber.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0.
+    pub fn new(capacity: usize) -> MultipartBroadcaster {
+        let boundary = MultipartWriter::new(MultipartType::XMixedReplace).boundary;
+        MultipartBroadcaster::with_boundary(boundary, capacity)
+    }
+
+    /// Like `new`, with a custom boundary
+    pub fn new_with_boundary(
+        boundary: &str,
+        capacity: usize,
+    ) -> Result<MultipartBroadcaster, MultipartError> {
+        writer::validate_boundary(boundary)?;
+        Ok(MultipartBroadcaster::with_boundary(
+            boundary.to_string(),
+            capacity,
+        ))
+    }
+
+    fn with_boundary(boundary: String, capacity: usize) -> MultipartBroadcaster {
+        assert!(capacity > 0, "capacity must be at least 1");
+
+        MultipartBroadcaster {
+            boundary,
+            capacity,
+            subscribers: Mutex::new(vec![]),
+        }
+    }
+
+    /// The Content-Type header value to send to subscribers
+    pub fn content_type(&self) -> String {
+        writer::content_type(&MultipartType::XMixedReplace, &self.boundary)
+    }
+
+    /// Number of currently connected subscribers
+    pub fn subscriber_count(&self) -> usize {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|queue| Arc::strong_count(queue) > 1);
+        subscribers.len()
+    }
+
+    /// Subscribe to the stream, starting with the next part that is sent
+    pub fn subscribe(&self) -> MultipartSubscriber {
+        let queue = Arc::new(Mutex::new(Queue::default()));
+        self.subscribers.lock().unwrap().push(queue.clone());
+
+        MultipartSubscriber { queue }
+    }
+
+    /// Send a part to all subscribers.
+    ///
+    /// Never waits for subscribers: those whose queue is full drop their oldest queued part,
+    /// those which went away are removed. Parts should be sent from a single thread to keep
+    /// their order.
+    pub fn send<B: Into<Bytes>>(
+        &self,
+        headers: &[(String, String)],
+        data: B,
+    ) -> Result<(), MultipartError> {
+        let data = data.into();
+        let mut frame = writer::encode_head(&self.boundary, headers, false)?;
+        frame.reserve(data.len() + 2);
+        frame.put_slice(&data);
+        frame.put_slice(b"\r\n");
+
+        let frame = frame.freeze();
+        self.broadcast(|queue| {
+            if queue.frames.len() >= self.capacity {
+                queue.frames.pop_front();
+            }
+            queue.frames.push_back(frame.clone());
+        });
+        Ok(())
+    }
+
+    /// Send the closing delimiter to all subscribers and end their streams.
+    ///
+    /// The delimiter is queued in addition to the parts, so lagging subscribers see it, too.
+    pub fn close(self) {
+        let closing = Bytes::from(format!("--{}--\r\n", self.boundary));
+        self.broadcast(|queue| {
+            queue.frames.push_back(closing.clone());
+            queue.closed = true;
+        });
+    }
+
+    fn broadcast<F: Fn(&mut Queue)>(&self, push: F) {
+        // Subscribers which went away only hold the queue in the list
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|queue| Arc::strong_count(queue) > 1);
+
+        for queue in subscribers.iter() {
+            let mut queue = queue.lock().unwrap();
+            push(&mut queue);
+            if let Some(waker) = queue.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl Stream for MultipartSubscriber {
+    type Item = Bytes;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut queue = self.queue.lock().unwrap();
+        match queue.frames.pop_front() {
+            Some(frame) => Poll::Ready(Some(frame)),
+            None if queue.closed => Poll::Ready(None),
+            None => {
+                queue.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use super::*;
+    use crate::reader::MultipartReader;
+
+    async fn read_frames(subscriber: MultipartSubscriber, boundary: &str) -> Vec<Bytes> {
+        let stream = subscriber.map(Ok::<_, std::io::Error>);
+        let mut reader = MultipartReader::from_stream_with_boundary_and_type(
+            stream,
+            boundary,
+            MultipartType::XMixedReplace,
+        )
+        .unwrap();
+
+        let mut bodies = vec![];
+        while let Some(item) = reader.next().await {
+            bodies.push(item.unwrap().data.freeze());
+        }
+        bodies
+    }
+
+    #[futures_test::test]
+    async fn subscribers_start_at_next_part() {
+        let broadcaster = MultipartBroadcaster::new_with_boundary("frame", 4).unwrap();
+        let headers = [("Content-Type".to_string(), "image/jpeg".to_string())];
+
+        let early = broadcaster.subscribe();
+        broadcaster.send(&headers, "first").unwrap();
+        let late = broadcaster.subscribe();
+        broadcaster.send(&headers, "second").unwrap();
+        assert_eq!(broadcaster.subscriber_count(), 2);
+        broadcaster.close();
+
+        assert_eq!(read_frames(early, "frame").await, vec!["first", "second"]);
+        assert_eq!(read_frames(late, "frame").await, vec!["second"]);
+    }
+
+    #[futures_test::test]
+    async fn drops_disconnected_subscribers() {
+        let broadcaster = MultipartBroadcaster::new(1);
+        drop(broadcaster.subscribe());
+
+        broadcaster.send(&[], "frame").unwrap();
+        assert_eq!(broadcaster.subscriber_count(), 0);
+    }
+
+    #[futures_test::test]
+    async fn lagging_subscriber_keeps_latest_parts() {
+        let broadcaster = MultipartBroadcaster::new_with_boundary("frame", 2).unwrap();
+        let stalled = broadcaster.subscribe();
+        let mut active = broadcaster.subscribe();
+
+        for frame in ["1", "2", "3", "4", "5"] {
+            broadcaster.send(&[], frame).unwrap();
+            let received = active.next().await.unwrap();
+            assert!(received.ends_with(format!("\r\n\r\n{}\r\n", frame).as_bytes()));
+        }
+        assert_eq!(broadcaster.subscriber_count(), 2);
+        broadcaster.close();
+
+        assert_eq!(active.next().await.unwrap(), "--frame--\r\n");
+        assert_eq!(read_frames(stalled, "frame").await, vec!["4", "5"]);
+    }
+}
@@ -0,0 +1,94 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
+};
+
+use bytes::Bytes;
+
+/// Key of a body registered in a `PartCache`, derived from the hash of its content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PartKey(u64);
+
+/// Reusable part bodies shared between writers.
+///
+/// Cloning the cache is cheap and all clones refer to the same bodies, so a file attached to
+/// many concurrently written documents is only kept in memory once.
+#[derive(Clone, Default)]
+pub struct PartCache {
+    bodies: Arc<Mutex<HashMap<PartKey, Arc<Bytes>>>>,
+}
+
+impl PartCache {
+    pub fn new() -> PartCache {
+        PartCache::default()
+    }
+
+    /// Register a body and return its key.
+    ///
+    /// If a body with the same content is already registered, the cached body is returned
+    /// and the passed one is dropped.
+    pub fn insert<B: Into<Bytes>>(&self, body: B) -> (PartKey, Arc<Bytes>) {
+        let body = body.into();
+        let mut hasher = DefaultHasher::new();
+        body.hash(&mut hasher);
+        let mut key = PartKey(hasher.finish());
+
+        let mut bodies = self.bodies.lock().unwrap();
+        loop {
+            match bodies.get(&key) {
+                Some(cached) if **cached == body => return (key, cached.clone()),
+                // Hash collision with different content, probe the next key
+                Some(_) => key = PartKey(key.0.wrapping_add(1)),
+                None => break,
+            }
+        }
+
+        let body = Arc::new(body);
+        bodies.insert(key, body.clone());
+        (key, body)
+    }
+
+    pub fn get(&self, key: PartKey) -> Option<Arc<Bytes>> {
+        self.bodies.lock().unwrap().get(&key).cloned()
+    }
+
+    /// Remove a body from the cache. Writers still holding it are not affected.
+    pub fn remove(&self, key: PartKey) -> Option<Arc<Bytes>> {
+        self.bodies.lock().unwrap().remove(&key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.bodies.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{multipart_type::MultipartType, writer::MultipartWriter};
+
+    #[test]
+    fn shares_bodies_between_writers() {
+        let cache = PartCache::new();
+        let (key, body) = cache.insert(vec![1u8; 1024]);
+        let (same_key, same_body) = cache.insert(vec![1u8; 1024]);
+        assert_eq!(key, same_key);
+        assert!(Arc::ptr_eq(&body, &same_body));
+        assert_eq!(cache.len(), 1);
+
+        let mut first = MultipartWriter::new(MultipartType::Mixed);
+        let mut second = MultipartWriter::new(MultipartType::Mixed);
+        for writer in [&mut first, &mut second] {
+            writer.append_cached(&[], &cache.get(key).unwrap()).unwrap();
+            // Skip the encoded delimiter and headers
+            writer.take_chunk().unwrap();
+            let chunk = writer.take_chunk().unwrap().unwrap();
+            assert_eq!(chunk.as_ptr(), body.as_ptr());
+        }
+    }
+}
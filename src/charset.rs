@@ -0,0 +1,95 @@
+use chardetng::EncodingDetector;
+use encoding_rs::Encoding;
+
+use crate::reader::{header_param, MultipartItem};
+
+/// Text of a part decoded from its declared or detected charset
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedText {
+    /// Name of the encoding, e.g. `windows-1252`
+    pub encoding: &'static str,
+
+    /// Whether the encoding was detected heuristically because no (known) charset was declared
+    pub guessed: bool,
+
+    /// The decoded text, with malformed sequences replaced by U+FFFD
+    pub text: String,
+}
+
+impl MultipartItem {
+    /// Decode a text part using its charset parameter, or a heuristic guess if it has none.
+    ///
+    /// Returns `None` for parts with a non-text Content-Type.
+    pub fn decode_text(&self) -> Option<DecodedText> {
+        let content_type = self.header("content-type");
+        if let Some(content_type) = content_type {
+            let is_text = content_type
+                .trim_start()
+                .get(..5)
+                .is_some_and(|t| t.eq_ignore_ascii_case("text/"));
+            if !is_text {
+                return None;
+            }
+        }
+
+        let declared = content_type
+            .and_then(|content_type| header_param(content_type, "charset"))
+            .and_then(|charset| Encoding::for_label(charset.as_bytes()));
+
+        let (encoding, guessed) = match declared {
+            Some(encoding) => (encoding, false),
+            None => {
+                let mut detector = EncodingDetector::new();
+                detector.feed(&self.data, true);
+                (detector.guess(None, true), true)
+            }
+        };
+
+        // A byte order mark overrides both
+        let (text, encoding, _) = encoding.decode(&self.data);
+        Some(DecodedText {
+            encoding: encoding.name(),
+            guessed,
+            text: text.into_owned(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+
+    use super::*;
+
+    fn item(content_type: Option<&str>, data: &[u8]) -> MultipartItem {
+        MultipartItem {
+            headers: content_type
+                .map(|ct| vec![("Content-Type".to_string(), ct.to_string())])
+                .unwrap_or_default(),
+            data: BytesMut::from(data),
+        }
+    }
+
+    #[test]
+    fn uses_declared_charset() {
+        let text = item(Some("text/plain; charset=ISO-8859-1"), b"caf\xe9")
+            .decode_text()
+            .unwrap();
+        assert_eq!(text.text, "café");
+        assert_eq!(text.encoding, "windows-1252");
+        assert!(!text.guessed);
+    }
+
+    #[test]
+    fn guesses_missing_charset() {
+        let original = "Größere Änderungen für die Übersetzung möglich";
+        let (latin1, _, _) = encoding_rs::WINDOWS_1252.encode(original);
+
+        let text = item(None, &latin1).decode_text().unwrap();
+        assert!(text.guessed);
+        assert_eq!(text.encoding, "windows-1252");
+        assert_eq!(text.text, original);
+
+        assert!(item(Some("image/png"), b"\x89PNG").decode_text().is_none());
+    }
+}
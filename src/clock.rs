@@ -0,0 +1,28 @@
+use std::time::SystemTime;
+
+/// Source of the current time used by the writer, e.g. for the manifest.
+///
+/// Inject a `FixedClock` for reproducible output.
+pub trait Clock {
+    fn now(&self) -> SystemTime;
+}
+
+/// The system time, the default
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+/// Always returns the same time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FixedClock(pub SystemTime);
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> SystemTime {
+        self.0
+    }
+}
@@ -0,0 +1,130 @@
+/// Known deviations from the RFC 2046 delimiter syntax which the reader can tolerate.
+///
+/// Legacy producers sometimes emit delimiters without the leading `--` or with a different
+/// number of dashes. Enable a profile only for sources known to need it, as a more lenient
+/// profile makes it more likely that body lines are mistaken for delimiters.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CompatProfile {
+    /// Accept delimiters without the leading `--`
+    pub missing_dashes: bool,
+
+    /// Accept delimiters with any number of leading dashes instead of exactly two
+    pub nonstandard_dashes: bool,
+}
+
+impl CompatProfile {
+    /// RFC 2046 delimiters only, the default
+    pub const STRICT: CompatProfile = CompatProfile {
+        missing_dashes: false,
+        nonstandard_dashes: false,
+    };
+
+    /// Producers which omit the leading dashes of delimiters
+    pub const MISSING_DASHES: CompatProfile = CompatProfile {
+        missing_dashes: true,
+        nonstandard_dashes: false,
+    };
+
+    /// Producers which emit a varying number of leading dashes
+    pub const NONSTANDARD_DASHES: CompatProfile = CompatProfile {
+        missing_dashes: false,
+        nonstandard_dashes: true,
+    };
+
+    /// All known deviations
+    pub const LENIENT: CompatProfile = CompatProfile {
+        missing_dashes: true,
+        nonstandard_dashes: true,
+    };
+
+    /// Strip the delimiter prefix and the boundary from a line, returning what follows
+    pub(crate) fn strip_delimiter<'d>(&self, line: &'d [u8], boundary: &str) -> Option<&'d [u8]> {
+        let delimiter = line.strip_prefix(b"--");
+        if let Some(rest) = delimiter.and_then(|l| l.strip_prefix(boundary.as_bytes())) {
+            return Some(rest);
+        }
+        if self.missing_dashes {
+            if let Some(rest) = line.strip_prefix(boundary.as_bytes()) {
+                return Some(rest);
+            }
+        }
+
+        // Boundaries often start with dashes on their own, which then count as prefix, too.
+        // Boundaries of dashes only are left to the exact syntax.
+        let core = boundary.trim_start_matches('-');
+        let dashes = line.iter().take_while(|c| **c == b'-').count();
+        if self.nonstandard_dashes && !core.is_empty() && dashes > 0 {
+            return line[dashes..].strip_prefix(core.as_bytes());
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use super::*;
+    use crate::{multipart_type::MultipartType, reader::MultipartReader};
+
+    async fn read(data: &[u8], profile: CompatProfile) -> Vec<Vec<u8>> {
+        let mut reader = MultipartReader::<std::io::Error>::from_data_with_boundary_and_type(
+            data,
+            "--boundary",
+            MultipartType::Mixed,
+        )
+        .unwrap()
+        .with_compat(profile);
+
+        let mut bodies = vec![];
+        while let Some(item) = reader.next().await {
+            bodies.push(item.unwrap().data.to_vec());
+        }
+        bodies
+    }
+
+    #[test]
+    fn strict_requires_two_dashes() {
+        let strict = CompatProfile::STRICT;
+        assert_eq!(
+            strict.strip_delimiter(b"----boundary", "--boundary"),
+            Some(&b""[..])
+        );
+        assert_eq!(strict.strip_delimiter(b"--boundary", "--boundary"), None);
+        assert_eq!(strict.strip_delimiter(b"---boundary", "--boundary"), None);
+    }
+
+    #[futures_test::test]
+    async fn dash_only_boundary() {
+        let data = b"------\r\n\r\nfirst\r\n------\r\n\r\nsecond\r\n--------\r\n";
+
+        for profile in [CompatProfile::STRICT, CompatProfile::LENIENT] {
+            let mut reader = MultipartReader::<std::io::Error>::from_data_with_boundary_and_type(
+                data,
+                "----",
+                MultipartType::Mixed,
+            )
+            .unwrap()
+            .with_compat(profile);
+
+            let mut bodies = vec![];
+            while let Some(item) = reader.next().await {
+                bodies.push(item.unwrap().data.to_vec());
+            }
+            assert_eq!(bodies, vec![b"first".to_vec(), b"second".to_vec()]);
+            assert!(reader.finished_cleanly());
+        }
+    }
+
+    #[futures_test::test]
+    async fn tolerates_deviating_delimiters() {
+        let data =
+            b"----boundary\r\n\r\nfirst\r\n--boundary\r\n\r\nsecond\r\n--------boundary--\r\n";
+
+        assert_eq!(read(data, CompatProfile::default()).await.len(), 0);
+        assert_eq!(
+            read(data, CompatProfile::LENIENT).await,
+            vec![b"first".to_vec(), b"second".to_vec()]
+        );
+    }
+}
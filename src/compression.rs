@@ -0,0 +1,254 @@
+use std::io::Read;
+
+use bytes::BytesMut;
+
+use crate::{error::MultipartError, reader::MultipartItem};
+
+/// Limits enforced while transparently decoding a compressed part
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecompressionLimits {
+    /// Maximum size of a decoded part body in bytes
+    pub max_decoded_size: usize,
+
+    /// Maximum ratio between the decoded and the encoded size of a part body
+    pub max_ratio: usize,
+}
+
+impl Default for DecompressionLimits {
+    fn default() -> Self {
+        DecompressionLimits {
+            max_decoded_size: 64 * 1024 * 1024,
+            max_ratio: 100,
+        }
+    }
+}
+
+/// Decode the body of an item according to its Content-Encoding or Transfer-Encoding header.
+///
+/// Unknown encodings (or encodings whose feature is disabled) leave the item untouched.
+pub(crate) fn decode_item(
+    item: &mut MultipartItem,
+    limits: &DecompressionLimits,
+) -> Result<(), MultipartError> {
+    let position = item.headers.iter().position(|(key, _)| {
+        key.eq_ignore_ascii_case("content-encoding")
+            || key.eq_ignore_ascii_case("transfer-encoding")
+    });
+    let Some(position) = position else {
+        return Ok(());
+    };
+
+    let encoding = item.headers[position].1.trim().to_lowercase();
+    let decoded = match decoder(&encoding, &item.data)? {
+        Some(decoder) => read_limited(decoder, item.data.len(), limits)?,
+        None => return Ok(()),
+    };
+
+    // The body is no longer encoded, so drop the header to avoid decoding it twice
+    item.headers.remove(position);
+    item.data = decoded;
+    Ok(())
+}
+
+/// Wrap a body source into an encoder for `encoding`
+pub(crate) fn encoder(
+    encoding: &str,
+    source: Box<dyn Read + Send>,
+) -> Result<Box<dyn Read + Send>, MultipartError> {
+    #[cfg(feature = "gzip")]
+    if encoding == "gzip" {
+        let encoder = flate2::read::GzEncoder::new(source, flate2::Compression::default());
+        return Ok(Box::new(encoder));
+    }
+
+    #[cfg(feature = "deflate")]
+    if encoding == "deflate" {
+        let encoder = flate2::read::ZlibEncoder::new(source, flate2::Compression::default());
+        return Ok(Box::new(encoder));
+    }
+
+    #[cfg(feature = "zstd")]
+    if encoding == "zstd" {
+        let encoder = zstd::stream::read::Encoder::new(source, zstd::DEFAULT_COMPRESSION_LEVEL)
+            .map_err(|_| MultipartError::ReadingSourceFailed)?;
+        return Ok(Box::new(encoder));
+    }
+
+    #[cfg(feature = "brotli")]
+    if encoding == "br" {
+        return Ok(Box::new(brotli::CompressorReader::new(source, 8192, 5, 22)));
+    }
+
+    if encoding == "identity" {
+        return Ok(source);
+    }
+
+    Err(MultipartError::UnsupportedEncoding)
+}
+
+#[allow(unused_variables)]
+fn decoder<'a>(
+    encoding: &str,
+    data: &'a [u8],
+) -> Result<Option<Box<dyn Read + 'a>>, MultipartError> {
+    #[cfg(feature = "gzip")]
+    if encoding == "gzip" || encoding == "x-gzip" {
+        return Ok(Some(Box::new(flate2::read::GzDecoder::new(data))));
+    }
+
+    #[cfg(feature = "deflate")]
+    if encoding == "deflate" {
+        return Ok(Some(Box::new(flate2::read::ZlibDecoder::new(data))));
+    }
+
+    #[cfg(feature = "zstd")]
+    if encoding == "zstd" {
+        let decoder = zstd::stream::read::Decoder::with_buffer(data)
+            .map_err(|_| MultipartError::DecompressionFailed)?;
+        return Ok(Some(Box::new(decoder)));
+    }
+
+    #[cfg(feature = "brotli")]
+    if encoding == "br" {
+        return Ok(Some(Box::new(brotli::Decompressor::new(data, 8192))));
+    }
+
+    Ok(None)
+}
+
+fn read_limited(
+    mut decoder: impl Read,
+    encoded_len: usize,
+    limits: &DecompressionLimits,
+) -> Result<BytesMut, MultipartError> {
+    let limit = limits
+        .max_decoded_size
+        .min(encoded_len.max(1).saturating_mul(limits.max_ratio));
+
+    let mut decoded = BytesMut::new();
+    let mut chunk = [0u8; 8192];
+    loop {
+        let len = decoder
+            .read(&mut chunk)
+            .map_err(|_| MultipartError::DecompressionFailed)?;
+        if len == 0 {
+            return Ok(decoded);
+        }
+
+        // Abort before buffering anything beyond the limit
+        if decoded.len() + len > limit {
+            return Err(MultipartError::DecompressionLimitExceeded);
+        }
+        decoded.extend_from_slice(&chunk[..len]);
+    }
+}
+
+#[cfg(all(test, feature = "gzip"))]
+mod tests {
+    use std::io::Write;
+
+    use futures_util::StreamExt;
+
+    use super::*;
+    use crate::{multipart_type::MultipartType, reader::MultipartReader};
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::best());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn document(body: &[u8]) -> Vec<u8> {
+        let mut data = b"--boundary\r\nContent-Encoding: gzip\r\n\r\n".to_vec();
+        data.extend_from_slice(body);
+        data.extend_from_slice(b"\r\n--boundary--\r\n");
+        data
+    }
+
+    #[futures_test::test]
+    async fn decodes_gzip_part() {
+        let body = gzip(b"Content of a.txt.");
+        let data = document(&body);
+        let mut reader = MultipartReader::<std::io::Error>::from_data_with_boundary_and_type(
+            &data,
+            "boundary",
+            MultipartType::Mixed,
+        )
+        .unwrap()
+        .with_decompression(DecompressionLimits::default())
+        .with_part_summaries();
+
+        let item = reader.next().await.unwrap().unwrap();
+        assert_eq!(&item.data[..], b"Content of a.txt.");
+        assert!(item.header("Content-Encoding").is_none());
+
+        // The summary describes the part as received
+        let summary = &reader.part_summaries()[0];
+        assert_eq!(summary.encoding.as_deref(), Some("gzip"));
+        assert_eq!(summary.size, Some(body.len() as u64));
+    }
+
+    #[futures_test::test]
+    async fn rejects_decompression_bomb() {
+        let data = document(&gzip(&vec![0u8; 1024 * 1024]));
+        let mut reader = MultipartReader::<std::io::Error>::from_data_with_boundary_and_type(
+            &data,
+            "boundary",
+            MultipartType::Mixed,
+        )
+        .unwrap()
+        .with_decompression(DecompressionLimits::default());
+
+        assert!(matches!(
+            reader.next().await,
+            Some(Err(MultipartError::DecompressionLimitExceeded))
+        ));
+    }
+}
+
+#[cfg(all(test, any(feature = "zstd", feature = "brotli")))]
+mod codec_tests {
+    use futures_util::StreamExt;
+
+    use super::*;
+    use crate::{
+        multipart_type::MultipartType, part::MultipartPart, reader::MultipartReader,
+        writer::MultipartWriter,
+    };
+
+    async fn roundtrip(encoding: &str) {
+        let body = "Content of a.txt. ".repeat(100);
+        let mut writer =
+            MultipartWriter::new_with_boundary("boundary", MultipartType::Mixed).unwrap();
+        let part = MultipartPart::new(body.clone())
+            .with_encoding(encoding)
+            .unwrap();
+        writer.append_part(part).unwrap();
+        let data = writer.finish().unwrap();
+        assert!(data.len() < body.len());
+
+        let mut reader = MultipartReader::<std::io::Error>::from_data_with_boundary_and_type(
+            &data,
+            "boundary",
+            MultipartType::Mixed,
+        )
+        .unwrap()
+        .with_decompression(DecompressionLimits::default());
+
+        let item = reader.next().await.unwrap().unwrap();
+        assert_eq!(&item.data[..], body.as_bytes());
+        assert!(item.header("Content-Encoding").is_none());
+    }
+
+    #[cfg(feature = "zstd")]
+    #[futures_test::test]
+    async fn roundtrips_zstd() {
+        roundtrip("zstd").await;
+    }
+
+    #[cfg(feature = "brotli")]
+    #[futures_test::test]
+    async fn roundtrips_brotli() {
+        roundtrip("br").await;
+    }
+}
@@ -0,0 +1,119 @@
+/// What the reader does with parts whose declared Content-Type is not allowed
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ContentTypePolicy {
+    /// Fail with `MultipartError::ContentTypeNotAllowed` and stop reading
+    #[default]
+    Reject,
+
+    /// Drop the part without buffering its body and continue with the next one
+    Skip,
+}
+
+/// Allow-list of part content types, see `MultipartReader::with_allowed_content_types`
+#[derive(Debug, Clone)]
+pub(crate) struct ContentTypeFilter {
+    types: Vec<String>,
+    pub(crate) policy: ContentTypePolicy,
+}
+
+impl ContentTypeFilter {
+    pub(crate) fn new(types: &[&str], policy: ContentTypePolicy) -> ContentTypeFilter {
+        ContentTypeFilter {
+            types: types
+                .iter()
+                .map(|t| t.trim().to_ascii_lowercase())
+                .collect(),
+            policy,
+        }
+    }
+
+    /// Whether a part with the given Content-Type header may pass.
+    ///
+    /// A missing header means `text/plain` (RFC 2046), except for form fields without a
+    /// filename (`is_field`), which always pass as they carry no upload.
+    pub(crate) fn allows(&self, content_type: Option<&str>, is_field: bool) -> bool {
+        let content_type = match content_type {
+            Some(content_type) => content_type,
+            None if is_field => return true,
+            None => "text/plain",
+        };
+        let essence = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+
+        self.types
+            .iter()
+            .any(|allowed| match allowed.strip_suffix("/*") {
+                // Wildcard subtypes like `image/*`
+                Some(type_) => essence.split_once('/').is_some_and(|(t, _)| t == type_),
+                None => *allowed == essence,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use super::*;
+    use crate::{error::MultipartError, multipart_type::MultipartType, reader::MultipartReader};
+
+    const DATA: &[u8] = b"--boundary\r
+Content-Disposition: form-data; name=\"a\"; filename=\"a.png\"\r
+Content-Type: IMAGE/PNG\r
+\r
+png\r
+--boundary\r
+Content-Disposition: form-data; name=\"b\"; filename=\"b.exe\"\r
+Content-Type: application/x-msdownload\r
+\r
+exe\r
+--boundary\r
+Content-Disposition: form-data; name=\"c\"\r
+\r
+field\r
+--boundary\r
+Content-Disposition: form-data; name=\"d\"; filename=\"evil.exe\"\r
+\r
+untyped\r
+--boundary--\r\n";
+
+    fn reader(policy: ContentTypePolicy) -> MultipartReader<'static, std::io::Error> {
+        MultipartReader::from_data_with_boundary_and_type(DATA, "boundary", MultipartType::FormData)
+            .unwrap()
+            .with_allowed_content_types(&["image/png", "image/jpeg"], policy)
+    }
+
+    #[futures_test::test]
+    async fn skips_or_rejects_other_types() {
+        let mut skipping = reader(ContentTypePolicy::Skip);
+        let mut names = vec![];
+        while let Some(item) = skipping.next().await {
+            names.push(item.unwrap().name().unwrap().to_string());
+        }
+        assert_eq!(names, vec!["a", "c"]);
+
+        let mut rejecting = reader(ContentTypePolicy::Reject);
+        assert!(rejecting.next().await.unwrap().is_ok());
+        assert!(matches!(
+            rejecting.next().await,
+            Some(Err(MultipartError::ContentTypeNotAllowed))
+        ));
+        assert!(rejecting.next().await.is_none());
+    }
+
+    #[test]
+    fn matches_wildcards() {
+        let filter = ContentTypeFilter::new(&["image/*"], ContentTypePolicy::Reject);
+        assert!(filter.allows(Some("image/webp; q=1"), false));
+        assert!(!filter.allows(Some("imagex/webp"), false));
+        assert!(filter.allows(None, true));
+        assert!(!filter.allows(None, false));
+
+        let text = ContentTypeFilter::new(&["text/*"], ContentTypePolicy::Reject);
+        assert!(text.allows(None, false));
+    }
+}
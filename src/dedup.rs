@@ -0,0 +1,130 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+/// Remembers the idempotency keys of parts which were already ingested.
+///
+/// Share one deduplicator (it is cheap to clone) between the readers of retried requests to
+/// drop re-sent parts. Only the `capacity` most recent keys are remembered.
+#[derive(Clone)]
+pub struct PartDeduplicator {
+    capacity: usize,
+    seen: Arc<Mutex<SeenKeys>>,
+}
+
+#[derive(Default)]
+struct SeenKeys {
+    keys: HashSet<String>,
+    /// Insertion order, used to evict the oldest keys
+    order: VecDeque<String>,
+}
+
+impl PartDeduplicator {
+    pub fn new(capacity: usize) -> PartDeduplicator {
+        PartDeduplicator {
+            capacity,
+            seen: Arc::new(Mutex::new(SeenKeys::default())),
+        }
+    }
+
+    /// Record a key, returns `false` if it was already seen
+    pub fn check(&self, key: &str) -> bool {
+        let mut seen = self.seen.lock().unwrap();
+        if seen.keys.contains(key) {
+            return false;
+        }
+        if self.capacity == 0 {
+            return true;
+        }
+
+        if seen.order.len() == self.capacity {
+            if let Some(oldest) = seen.order.pop_front() {
+                seen.keys.remove(&oldest);
+            }
+        }
+        seen.keys.insert(key.to_string());
+        seen.order.push_back(key.to_string());
+        true
+    }
+
+    /// Whether a key was already seen, without recording it
+    pub fn contains(&self, key: &str) -> bool {
+        self.seen.lock().unwrap().keys.contains(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use super::*;
+    use crate::{multipart_type::MultipartType, reader::MultipartReader};
+
+    #[test]
+    fn evicts_oldest_keys() {
+        let dedup = PartDeduplicator::new(2);
+        assert!(dedup.check("a"));
+        assert!(!dedup.check("a"));
+        assert!(dedup.check("b"));
+        assert!(dedup.check("c"));
+        assert!(!dedup.contains("a"));
+        assert!(dedup.check("a"));
+    }
+
+    #[futures_test::test]
+    async fn skips_resent_parts() {
+        let data = b"--boundary\r
+Content-ID: <part1@example.com>\r
+\r
+first\r
+--boundary\r
+Idempotency-Key: 8e03978e\r
+\r
+second\r
+--boundary--\r\n";
+        let dedup = PartDeduplicator::new(16);
+
+        // The first delivery yields everything, the retry yields nothing
+        for expected in [2, 0] {
+            let mut reader = MultipartReader::<std::io::Error>::from_data_with_boundary_and_type(
+                data,
+                "boundary",
+                MultipartType::Related,
+            )
+            .unwrap()
+            .with_deduplicator(dedup.clone());
+
+            let mut keys = vec![];
+            while let Some(item) = reader.next().await {
+                keys.push(item.unwrap().idempotency_key().unwrap().to_string());
+            }
+            assert_eq!(keys.len(), expected);
+            assert!(reader.finished_cleanly());
+        }
+    }
+
+    #[futures_test::test]
+    async fn reads_parts_of_truncated_attempt_again() {
+        let complete = b"--boundary\r
+Idempotency-Key: 8e03978e\r
+\r
+second\r
+--boundary--\r\n";
+        let truncated = &complete[..complete.len() - 20];
+        let dedup = PartDeduplicator::new(16);
+
+        for (data, expected) in [(truncated, 0), (&complete[..], 1), (&complete[..], 0)] {
+            let reader = MultipartReader::<std::io::Error>::from_data_with_boundary_and_type(
+                data,
+                "boundary",
+                MultipartType::Related,
+            )
+            .unwrap()
+            .with_deduplicator(dedup.clone());
+
+            let items: Vec<_> = reader.collect().await;
+            assert_eq!(items.len(), expected);
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use std::fmt::Write;
+
+use crate::reader::header_param;
+
+/// Metadata of a part, recorded without its body
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PartSummary {
+    pub name: Option<String>,
+    pub filename: Option<String>,
+    pub content_type: Option<String>,
+
+    /// Content-Encoding or Content-Transfer-Encoding of the body
+    pub encoding: Option<String>,
+
+    /// Size of the body in bytes as transferred, i.e. before it is decoded, if known
+    pub size: Option<u64>,
+}
+
+impl PartSummary {
+    pub(crate) fn from_headers(headers: &[(String, String)], size: Option<u64>) -> PartSummary {
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.trim())
+        };
+        let disposition = header("content-disposition");
+        let param = |name| {
+            disposition
+                .and_then(|d| header_param(d, name))
+                .map(str::to_string)
+        };
+
+        PartSummary {
+            name: param("name"),
+            filename: param("filename"),
+            content_type: header("content-type").map(str::to_string),
+            encoding: header("content-encoding")
+                .or_else(|| header("content-transfer-encoding"))
+                .map(str::to_string),
+            size,
+        }
+    }
+}
+
+/// Render a tree of the parts of a document, e.g.
+///
+/// ```text
+/// multipart/form-data; boundary=xyz (2 parts)
+/// ├─ name="text" size=12
+/// └─ name="file1" filename="a.txt" type=text/plain encoding=gzip size=18
+/// ```
+pub(crate) fn describe(content_type: &str, parts: &[PartSummary]) -> String {
+    let mut tree = format!("{} ({} parts)", content_type, parts.len());
+
+    for (idx, part) in parts.iter().enumerate() {
+        let connector = if idx + 1 == parts.len() {
+            "└─"
+        } else {
+            "├─"
+        };
+        let _ = write!(tree, "\n{}", connector);
+
+        if let Some(name) = &part.name {
+            let _ = write!(tree, " name=\"{}\"", name);
+        }
+        if let Some(filename) = &part.filename {
+            let _ = write!(tree, " filename=\"{}\"", filename);
+        }
+        if let Some(content_type) = &part.content_type {
+            let _ = write!(tree, " type={}", content_type);
+        }
+        if let Some(encoding) = &part.encoding {
+            let _ = write!(tree, " encoding={}", encoding);
+        }
+        match part.size {
+            Some(size) => {
+                let _ = write!(tree, " size={}", size);
+            }
+            None => tree.push_str(" size=?"),
+        }
+    }
+
+    tree
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use crate::{multipart_type::MultipartType, reader::MultipartReader, writer::MultipartWriter};
+
+    #[futures_test::test]
+    async fn describes_both_sides() {
+        let mut writer =
+            MultipartWriter::new_with_boundary("boundary", MultipartType::FormData).unwrap();
+        writer
+            .append(
+                &[(
+                    "Content-Disposition".to_string(),
+                    "form-data; name=\"text\"".to_string(),
+                )],
+                "text default",
+            )
+            .unwrap();
+        writer
+            .append_reader(
+                &[
+                    (
+                        "Content-Disposition".to_string(),
+                        "form-data; name=\"file1\"; filename=\"a.txt\"".to_string(),
+                    ),
+                    ("Content-Type".to_string(), "text/plain".to_string()),
+                ],
+                &b"Content of a.txt."[..],
+            )
+            .unwrap();
+
+        assert_eq!(
+            writer.describe(),
+            "multipart/form-data; boundary=boundary (2 parts)
+├─ name=\"text\" size=12
+└─ name=\"file1\" filename=\"a.txt\" type=text/plain size=?"
+        );
+
+        let data = writer.finish().unwrap();
+
+        // Recorded bodies report their size once they were emitted
+        let mut recorded = MultipartWriter::new_with_boundary("boundary", MultipartType::FormData)
+            .unwrap()
+            .with_manifest();
+        recorded
+            .append_reader(&[], &b"Content of a.txt."[..])
+            .unwrap();
+        while recorded.take_chunk().unwrap().is_some() {}
+        assert!(recorded.describe().ends_with("└─ size=17"));
+        let mut reader = MultipartReader::<std::io::Error>::from_data_with_boundary_and_type(
+            &data,
+            "boundary",
+            MultipartType::FormData,
+        )
+        .unwrap()
+        .with_part_summaries();
+        while reader.next().await.is_some() {}
+
+        assert_eq!(
+            reader.describe(),
+            "multipart/form-data; boundary=boundary (2 parts)
+├─ name=\"text\" size=12
+└─ name=\"file1\" filename=\"a.txt\" type=text/plain size=17"
+        );
+    }
+}
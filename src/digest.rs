@@ -0,0 +1,89 @@
+/// Digest of the part bodies listed by the manifest and the trailer of a writer.
+///
+/// The default `Crc32` only detects accidental corruption. To verify the integrity of
+/// documents, implement this trait for a cryptographic hash like SHA-256 of a crypto crate.
+pub trait BodyDigest: Send {
+    /// Name of the algorithm listed in the manifest, e.g. `sha256`
+    fn algorithm(&self) -> &str;
+
+    /// A new digest of the same algorithm for the next body
+    fn start(&self) -> Box<dyn BodyDigest>;
+
+    /// Feed the next piece of the body
+    fn update(&mut self, data: &[u8]);
+
+    /// Return the digest after the whole body was fed, which is listed hex-encoded
+    fn finish(&self) -> Vec<u8>;
+}
+
+/// CRC-32 (IEEE 802.3) lookup table
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-32 checksum, the default digest, which detects accidental corruption but no tampering
+#[derive(Debug, Clone, Copy)]
+pub struct Crc32(u32);
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Crc32(0xFFFF_FFFF)
+    }
+}
+
+impl Crc32 {
+    /// The checksum of the data fed so far
+    pub fn value(&self) -> u32 {
+        !self.0
+    }
+}
+
+impl BodyDigest for Crc32 {
+    fn algorithm(&self) -> &str {
+        "crc32"
+    }
+
+    fn start(&self) -> Box<dyn BodyDigest> {
+        Box::new(Crc32::default())
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        for byte in data {
+            self.0 = CRC32_TABLE[((self.0 ^ *byte as u32) & 0xFF) as usize] ^ (self.0 >> 8);
+        }
+    }
+
+    fn finish(&self) -> Vec<u8> {
+        self.value().to_be_bytes().to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_crc32() {
+        let mut crc = Crc32::default();
+        crc.update(b"1234");
+        crc.update(b"56789");
+        assert_eq!(crc.value(), 0xCBF4_3926);
+        assert_eq!(crc.finish(), [0xCB, 0xF4, 0x39, 0x26]);
+    }
+}
@@ -1,7 +1,6 @@
 use std::{
     error::Error,
     fmt::{Display, Formatter, Result},
-    ops::Mul,
 };
 
 #[derive(Debug)]
@@ -23,6 +22,54 @@ pub enum MultipartError {
 
     // Failed to poll data from the stream
     PollingDataFailed,
+
+    // Failed to decompress a part
+    DecompressionFailed,
+
+    // Decompressed part exceeds the configured limits
+    DecompressionLimitExceeded,
+
+    // Text field is not valid UTF-8
+    InvalidUtf8,
+
+    // Failed to read data from a part source
+    ReadingSourceFailed,
+
+    // Failed to write the encoded document
+    WritingDataFailed,
+
+    // Expected a part, but the document has none
+    MissingPart,
+
+    // Expected a single part, but the document has more
+    TooManyParts,
+
+    // Nested documents exceed the maximum depth
+    NestingTooDeep,
+
+    // Document ended without the terminating boundary
+    IncompleteDocument,
+
+    // Content encoding is not supported
+    UnsupportedEncoding,
+
+    // Buffer is too small for the body of a part
+    BufferTooSmall,
+
+    // An external quota check stopped the reader
+    QuotaExceeded,
+
+    // Saved reader state is malformed or from an incompatible version
+    InvalidReaderState,
+
+    // Declared content type of a part is not allowed
+    ContentTypeNotAllowed,
+
+    // Encoded document would exceed the maximum size
+    DocumentTooLarge,
+
+    // First boundary was not found within the preamble limits
+    PreambleTooLong,
 }
 
 impl Display for MultipartError {
@@ -34,6 +81,38 @@ impl Display for MultipartError {
             MultipartError::InvalidMultipartType => write!(f, "Invalid Multipart type"),
             MultipartError::InvalidItemHeader => write!(f, "Invalid Item header"),
             MultipartError::PollingDataFailed => write!(f, "Failed to poll data from the stream"),
+            MultipartError::DecompressionFailed => write!(f, "Failed to decompress part"),
+            MultipartError::DecompressionLimitExceeded => {
+                write!(f, "Decompressed part exceeds the configured limits")
+            }
+            MultipartError::InvalidUtf8 => write!(f, "Text field is not valid UTF-8"),
+            MultipartError::ReadingSourceFailed => {
+                write!(f, "Failed to read data from a part source")
+            }
+            MultipartError::WritingDataFailed => write!(f, "Failed to write the encoded document"),
+            MultipartError::MissingPart => write!(f, "Expected a part, but the document has none"),
+            MultipartError::TooManyParts => {
+                write!(f, "Expected a single part, but the document has more")
+            }
+            MultipartError::NestingTooDeep => {
+                write!(f, "Nested documents exceed the maximum depth")
+            }
+            MultipartError::IncompleteDocument => {
+                write!(f, "Document ended without the terminating boundary")
+            }
+            MultipartError::UnsupportedEncoding => write!(f, "Content encoding is not supported"),
+            MultipartError::BufferTooSmall => write!(f, "Buffer is too small for the part"),
+            MultipartError::QuotaExceeded => write!(f, "Quota exceeded"),
+            MultipartError::InvalidReaderState => write!(f, "Reader state is invalid"),
+            MultipartError::ContentTypeNotAllowed => {
+                write!(f, "Content type of the part is not allowed")
+            }
+            MultipartError::DocumentTooLarge => {
+                write!(f, "Document exceeds the maximum size")
+            }
+            MultipartError::PreambleTooLong => {
+                write!(f, "No boundary found within the preamble limits")
+            }
         }
     }
 }
@@ -0,0 +1,123 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Seek, SeekFrom, Write},
+};
+
+use crate::{error::MultipartError, writer::MultipartWriter};
+
+/// Writes an encoded document into a file instead of memory.
+///
+/// Useful for huge documents, the returned file can be sent with `sendfile`-style APIs
+/// (e.g. after converting it into an async file of the runtime).
+pub struct FileSink {
+    file: BufWriter<File>,
+    len: u64,
+}
+
+/// A document written by a `FileSink`
+#[derive(Debug)]
+pub struct EncodedFile {
+    /// The file, positioned at the start of the document
+    pub file: File,
+
+    /// Length of the document in bytes
+    pub len: u64,
+}
+
+impl FileSink {
+    /// Write into `file`, starting at its current position
+    pub fn new(file: File) -> FileSink {
+        FileSink {
+            file: BufWriter::new(file),
+            len: 0,
+        }
+    }
+
+    /// Write into an anonymous temporary file, which is removed once it is closed
+    #[cfg(feature = "tempfile")]
+    pub fn temp() -> Result<FileSink, MultipartError> {
+        let file = tempfile::tempfile().map_err(|_| MultipartError::WritingDataFailed)?;
+        Ok(FileSink::new(file))
+    }
+
+    /// Number of bytes written so far
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Move all chunks encoded by the writer so far into the file.
+    ///
+    /// Call this after appending parts to keep the memory usage of the writer low.
+    pub fn drain(&mut self, writer: &mut MultipartWriter) -> Result<(), MultipartError> {
+        while let Some(chunk) = writer.take_chunk()? {
+            self.write(&chunk)?;
+        }
+        Ok(())
+    }
+
+    /// Close the document and return the file rewound to its start
+    pub fn finish(mut self, mut writer: MultipartWriter) -> Result<EncodedFile, MultipartError> {
+        self.drain(&mut writer)?;
+        self.write(&writer.finish()?)?;
+
+        let mut file = self
+            .file
+            .into_inner()
+            .map_err(|_| MultipartError::WritingDataFailed)?;
+        file.seek(SeekFrom::Current(-(self.len as i64)))
+            .map_err(|_| MultipartError::WritingDataFailed)?;
+
+        Ok(EncodedFile {
+            file,
+            len: self.len,
+        })
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), MultipartError> {
+        self.file
+            .write_all(data)
+            .map_err(|_| MultipartError::WritingDataFailed)?;
+        self.len += data.len() as u64;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+    use crate::multipart_type::MultipartType;
+
+    #[test]
+    fn writes_document_to_file() {
+        let path = std::env::temp_dir().join(format!("multipart-rs-{}.bin", std::process::id()));
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+
+        let mut writer =
+            MultipartWriter::new_with_boundary("boundary", MultipartType::Mixed).unwrap();
+        let mut sink = FileSink::new(file);
+        writer.append(&[], "first").unwrap();
+        sink.drain(&mut writer).unwrap();
+        writer.append(&[], "second").unwrap();
+
+        let mut encoded = sink.finish(writer).unwrap();
+        let mut data = vec![];
+        encoded.file.read_to_end(&mut data).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let expected = b"--boundary\r\n\r\nfirst\r\n--boundary\r\n\r\nsecond\r\n--boundary--\r\n";
+        assert_eq!(data, expected);
+        assert_eq!(encoded.len, expected.len() as u64);
+    }
+}
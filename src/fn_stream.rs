@@ -0,0 +1,103 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use futures_channel::mpsc;
+use futures_core::{future::LocalBoxFuture, Stream};
+use futures_util::{future::poll_fn, FutureExt};
+
+/// Handle passed to the generator of a `FnStream` to yield chunks
+pub struct ChunkSender<E> {
+    sender: mpsc::Sender<Result<Bytes, E>>,
+}
+
+impl<E> ChunkSender<E> {
+    /// Yield a chunk, waiting until the consumer asks for more data
+    pub async fn send(&mut self, chunk: Result<Bytes, E>) {
+        // The receiver lives as long as the generator, so sending can't fail
+        if poll_fn(|cx| self.sender.poll_ready(cx)).await.is_ok() {
+            let _ = self.sender.start_send(chunk);
+        }
+    }
+}
+
+/// A stream of chunks produced by an async generator function.
+///
+/// The generator is driven by polling the stream and ends the stream by returning. Mostly
+/// used through `MultipartReader::from_fn_stream`, but it can be passed to any constructor
+/// taking a stream.
+pub struct FnStream<'a, E> {
+    generator: Option<LocalBoxFuture<'a, ()>>,
+    receiver: mpsc::Receiver<Result<Bytes, E>>,
+}
+
+impl<'a, E> FnStream<'a, E> {
+    pub fn new<F, Fut>(generator: F) -> FnStream<'a, E>
+    where
+        F: FnOnce(ChunkSender<E>) -> Fut,
+        Fut: Future<Output = ()> + 'a,
+    {
+        // Each sender owns one slot, so the generator is at most one chunk ahead
+        let (sender, receiver) = mpsc::channel(0);
+
+        FnStream {
+            generator: Some(generator(ChunkSender { sender }).boxed_local()),
+            receiver,
+        }
+    }
+}
+
+impl<'a, E> Stream for FnStream<'a, E> {
+    type Item = Result<Bytes, E>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        // Drive the generator, dropping it (and thereby closing the channel) once it returns
+        if let Some(generator) = &mut this.generator {
+            if generator.as_mut().poll(cx).is_ready() {
+                this.generator = None;
+            }
+        }
+
+        Pin::new(&mut this.receiver).poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use crate::reader::MultipartReader;
+
+    #[futures_test::test]
+    async fn reads_from_generator() {
+        let headers = vec![(
+            "Content-Type".to_string(),
+            "multipart/mixed; boundary=boundary".to_string(),
+        )];
+        let frames: Vec<&'static [u8]> = vec![
+            b"--boundary\r\n\r\nfirst\r\n--bound",
+            b"",
+            b"ary\r\n\r\nsecond\r\n--boundary--\r\n",
+        ];
+
+        let mut reader = MultipartReader::<std::io::Error>::from_fn_stream(
+            |mut sender| async move {
+                for frame in frames {
+                    sender.send(Ok(frame.into())).await;
+                }
+            },
+            &headers,
+        )
+        .unwrap();
+
+        assert_eq!(&reader.next().await.unwrap().unwrap().data[..], b"first");
+        assert_eq!(&reader.next().await.unwrap().unwrap().data[..], b"second");
+        assert!(reader.next().await.is_none());
+        assert!(reader.finished_cleanly());
+    }
+}
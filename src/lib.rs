@@ -1,9 +1,73 @@
+#[cfg(feature = "actix")]
+mod actix;
+#[cfg(feature = "blocking")]
+mod blocking;
+mod boundary;
+mod broadcast;
+mod cache;
+#[cfg(feature = "chardetng")]
+mod charset;
+mod clock;
+mod codec;
+mod compat;
+mod compression;
+mod content_filter;
+mod dedup;
+mod describe;
+mod digest;
 mod error;
+mod file_sink;
+mod fn_stream;
+mod line_buffer;
+mod manifest;
+mod media_type;
 mod multipart_type;
+#[cfg(feature = "otel")]
+mod otel;
+mod part;
+mod passthrough;
+mod preamble;
+mod pump;
+mod quota;
 mod reader;
+#[cfg(feature = "serde")]
+mod spec;
+#[cfg(feature = "serde")]
+mod state;
+mod text;
 mod writer;
 
+#[cfg(feature = "actix")]
+pub use actix::*;
+#[cfg(feature = "blocking")]
+pub use blocking::*;
+pub use boundary::*;
+pub use broadcast::*;
+pub use cache::*;
+#[cfg(feature = "chardetng")]
+pub use charset::*;
+pub use clock::*;
+pub use codec::*;
+pub use compat::*;
+pub use compression::*;
+pub use content_filter::*;
+pub use dedup::*;
+pub use describe::*;
+pub use digest::*;
 pub use error::*;
+pub use file_sink::*;
+pub use fn_stream::*;
 pub use multipart_type::*;
+#[cfg(feature = "otel")]
+pub use otel::*;
+pub use part::*;
+pub use preamble::*;
+pub use pump::*;
+pub use quota::*;
 pub use reader::*;
+#[cfg(feature = "serde")]
+pub use spec::*;
+#[cfg(feature = "serde")]
+pub use state::*;
+pub use text::*;
 pub use writer::*;
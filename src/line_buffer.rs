@@ -0,0 +1,75 @@
+use std::ops::Deref;
+
+use bytes::{Buf, BytesMut};
+
+/// Reassembly buffer for the line-based parser.
+///
+/// Sources may deliver the document in arbitrarily small (or empty) chunks, so a line can be
+/// completed by many appends. The buffer remembers how far it already searched for a line
+/// break, which keeps finding lines linear in the size of the document instead of rescanning
+/// the whole pending line after every chunk.
+#[derive(Default)]
+pub(crate) struct LineBuffer {
+    buf: BytesMut,
+    /// Number of bytes known not to start a CRLF sequence
+    scanned: usize,
+}
+
+impl LineBuffer {
+    pub(crate) fn extend(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Returns the position of the next CRLF sequence, if the buffer holds a complete line
+    pub(crate) fn find_line(&mut self) -> Option<usize> {
+        match memchr::memmem::find(&self.buf[self.scanned..], b"\r\n") {
+            Some(idx) => {
+                self.scanned += idx;
+                Some(self.scanned)
+            }
+            None => {
+                // A trailing CR may be completed by the next chunk
+                self.scanned = self.buf.len().saturating_sub(1);
+                None
+            }
+        }
+    }
+
+    /// Discard the first `cnt` bytes
+    pub(crate) fn advance(&mut self, cnt: usize) {
+        self.buf.advance(cnt);
+        self.scanned = self.scanned.saturating_sub(cnt);
+    }
+}
+
+impl Deref for LineBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_lines_split_across_appends() {
+        let mut buf = LineBuffer::default();
+        for chunk in [&b"ab"[..], b"", b"c\r", b"", b"\nde\r\n"] {
+            buf.extend(chunk);
+        }
+        assert_eq!(buf.find_line(), Some(3));
+        buf.advance(5);
+        assert_eq!(buf.find_line(), Some(2));
+        buf.advance(4);
+        assert_eq!(buf.find_line(), None);
+        assert!(buf.is_empty());
+
+        buf.extend(b"x\r");
+        assert_eq!(buf.find_line(), None);
+        buf.extend(b"\n");
+        assert_eq!(buf.find_line(), Some(1));
+    }
+}
@@ -0,0 +1,171 @@
+use std::{fmt::Write, time::SystemTime};
+
+use crate::{describe::PartSummary, digest::BodyDigest};
+
+/// Size and checksum of a body, recorded while it is emitted
+pub(crate) struct BodyRecord {
+    pub(crate) size: u64,
+    pub(crate) checksum: Box<dyn BodyDigest>,
+}
+
+impl BodyRecord {
+    pub(crate) fn new(checksum: Box<dyn BodyDigest>) -> BodyRecord {
+        BodyRecord { size: 0, checksum }
+    }
+
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        self.size += data.len() as u64;
+        self.checksum.update(data);
+    }
+}
+
+/// Render the manifest of a document as JSON.
+///
+/// `records` holds one entry per part, parts whose body was not recorded take the size from
+/// their summary and have a `null` checksum. `algorithm` names the checksums.
+pub(crate) fn to_json(
+    content_type: &str,
+    size: u64,
+    created: SystemTime,
+    algorithm: &str,
+    summaries: &[PartSummary],
+    records: &[Option<BodyRecord>],
+) -> String {
+    let mut json = String::new();
+    json.push_str("{\"content_type\":");
+    push_string(&mut json, Some(content_type));
+    let created = created
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let _ = write!(json, ",\"size\":{},\"created\":{},", size, created);
+    push_algorithm(&mut json, algorithm);
+    push_parts(&mut json, summaries, records);
+    json.push('}');
+    json
+}
+
+/// Render the trailer part, `size` being the number of bytes in front of it
+pub(crate) fn trailer_json(
+    size: u64,
+    algorithm: &str,
+    summaries: &[PartSummary],
+    records: &[Option<BodyRecord>],
+) -> String {
+    let mut json = String::new();
+    let _ = write!(json, "{{\"count\":{},\"size\":{},", summaries.len(), size);
+    push_algorithm(&mut json, algorithm);
+    push_parts(&mut json, summaries, records);
+    json.push('}');
+    json
+}
+
+/// Upper bound of the length of a part listed in the trailer, whatever size and checksum of
+/// `digest` are recorded for it
+pub(crate) fn part_len_bound(summary: &PartSummary, digest: &dyn BodyDigest) -> u64 {
+    let summary = PartSummary {
+        size: Some(u64::MAX),
+        ..summary.clone()
+    };
+    let mut json = String::new();
+    push_parts(
+        &mut json,
+        &[summary],
+        &[Some(BodyRecord::new(digest.start()))],
+    );
+
+    // The part without the brackets of the list, but with a separating comma
+    (json.len() - "\"parts\":[]".len() + 1) as u64
+}
+
+/// Upper bound of the length of the trailer whose parts take up to `parts_len` bytes
+pub(crate) fn trailer_len_bound(algorithm: &str, parts_len: u64) -> u64 {
+    let mut json = format!("{{\"count\":{},\"size\":{},", usize::MAX, u64::MAX);
+    push_algorithm(&mut json, algorithm);
+    json.push_str("\"parts\":[]}");
+    json.len() as u64 + parts_len
+}
+
+/// Append the `checksum_algorithm` member
+fn push_algorithm(json: &mut String, algorithm: &str) {
+    json.push_str("\"checksum_algorithm\":");
+    push_string(json, Some(algorithm));
+    json.push(',');
+}
+
+/// Append the `parts` member listing every part
+fn push_parts(json: &mut String, summaries: &[PartSummary], records: &[Option<BodyRecord>]) {
+    json.push_str("\"parts\":[");
+    for (idx, summary) in summaries.iter().enumerate() {
+        if idx > 0 {
+            json.push(',');
+        }
+        let record = records.get(idx).and_then(Option::as_ref);
+
+        json.push_str("{\"name\":");
+        push_string(json, summary.name.as_deref());
+        json.push_str(",\"filename\":");
+        push_string(json, summary.filename.as_deref());
+        json.push_str(",\"content_type\":");
+        push_string(json, summary.content_type.as_deref());
+        json.push_str(",\"encoding\":");
+        push_string(json, summary.encoding.as_deref());
+
+        json.push_str(",\"size\":");
+        match record.map(|record| record.size).or(summary.size) {
+            Some(size) => {
+                let _ = write!(json, "{}", size);
+            }
+            None => json.push_str("null"),
+        }
+
+        json.push_str(",\"checksum\":");
+        match record {
+            Some(record) => {
+                json.push('"');
+                for byte in record.checksum.finish() {
+                    let _ = write!(json, "{:02x}", byte);
+                }
+                json.push('"');
+            }
+            None => json.push_str("null"),
+        }
+        json.push('}');
+    }
+    json.push(']');
+}
+
+/// Append a JSON string literal, or `null`
+fn push_string(json: &mut String, value: Option<&str>) {
+    let Some(value) = value else {
+        json.push_str("null");
+        return;
+    };
+
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_strings() {
+        let mut json = String::new();
+        push_string(&mut json, Some("a \"b\"\\\n\u{1}"));
+        assert_eq!(json, r#""a \"b\"\\\n\u0001""#);
+    }
+}
@@ -0,0 +1,85 @@
+use crate::{error::MultipartError, multipart_type::MultipartType};
+
+/// Parse a multipart Content-Type header value into the subtype and the boundary
+pub(crate) fn parse_multipart(value: &str) -> Result<(MultipartType, String), MultipartError> {
+    let (type_, subtype, boundary) = parse(value)?;
+    let boundary = boundary.ok_or(MultipartError::InvalidBoundary)?;
+
+    if !type_.eq_ignore_ascii_case("multipart") {
+        return Err(MultipartError::InvalidContentType);
+    }
+
+    let multipart_type = subtype
+        .parse::<MultipartType>()
+        .map_err(|_| MultipartError::InvalidMultipartType)?;
+    Ok((multipart_type, boundary))
+}
+
+#[cfg(feature = "mime")]
+fn parse(value: &str) -> Result<(String, String, Option<String>), MultipartError> {
+    let ct = value
+        .parse::<mime::Mime>()
+        .map_err(|_e| MultipartError::InvalidContentType)?;
+    let boundary = ct.get_param(mime::BOUNDARY).map(|b| b.to_string());
+
+    Ok((ct.type_().to_string(), ct.subtype().to_string(), boundary))
+}
+
+/// Minimal parser for builds without the `mime` crate, which only extracts what the reader needs
+#[cfg(not(feature = "mime"))]
+fn parse(value: &str) -> Result<(String, String, Option<String>), MultipartError> {
+    let essence = value.split(';').next().unwrap_or_default().trim();
+    let (type_, subtype) = essence
+        .split_once('/')
+        .ok_or(MultipartError::InvalidContentType)?;
+
+    let is_token = |s: &str| {
+        !s.is_empty()
+            && s.bytes()
+                .all(|c| c.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&c))
+    };
+    if !is_token(type_) || !is_token(subtype) {
+        return Err(MultipartError::InvalidContentType);
+    }
+
+    let boundary = crate::reader::header_param(value, "boundary").map(str::to_string);
+    Ok((
+        type_.to_ascii_lowercase(),
+        subtype.to_ascii_lowercase(),
+        boundary,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_multipart_content_types() {
+        assert_eq!(
+            parse_multipart("multipart/form-data; boundary=abc").unwrap(),
+            (MultipartType::FormData, "abc".to_string())
+        );
+        assert_eq!(
+            parse_multipart("Multipart/Mixed; charset=utf-8; boundary=\"a b\"").unwrap(),
+            (MultipartType::Mixed, "a b".to_string())
+        );
+
+        assert!(matches!(
+            parse_multipart("multipart/mixed"),
+            Err(MultipartError::InvalidBoundary)
+        ));
+        assert!(matches!(
+            parse_multipart("text/plain; boundary=abc"),
+            Err(MultipartError::InvalidContentType)
+        ));
+        assert!(matches!(
+            parse_multipart("multipart/unknown; boundary=abc"),
+            Err(MultipartError::InvalidMultipartType)
+        ));
+        assert!(matches!(
+            parse_multipart("no-slash; boundary=abc"),
+            Err(MultipartError::InvalidContentType)
+        ));
+    }
+}
@@ -1,4 +1,7 @@
-use std::str::FromStr;
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
 
 use crate::error::MultipartError;
 
@@ -18,6 +21,12 @@ pub enum MultipartType {
 
     // Related - RFC 2387
     Related,
+
+    // X-Mixed-Replace - server push, e.g. MJPEG streams
+    XMixedReplace,
+
+    // Byteranges - RFC 7233, responses to range requests
+    ByteRanges,
 }
 
 impl FromStr for MultipartType {
@@ -30,7 +39,35 @@ impl FromStr for MultipartType {
             "alternative" => Ok(MultipartType::Alternative),
             "digest" => Ok(MultipartType::Digest),
             "related" => Ok(MultipartType::Related),
+            "x-mixed-replace" => Ok(MultipartType::XMixedReplace),
+            "byteranges" => Ok(MultipartType::ByteRanges),
             _ => Err(MultipartError::InvalidMultipartType),
         }
     }
 }
+
+impl Display for MultipartType {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            MultipartType::FormData => write!(f, "form-data"),
+            MultipartType::Mixed => write!(f, "mixed"),
+            MultipartType::Alternative => write!(f, "alternative"),
+            MultipartType::Digest => write!(f, "digest"),
+            MultipartType::Related => write!(f, "related"),
+            MultipartType::XMixedReplace => write!(f, "x-mixed-replace"),
+            MultipartType::ByteRanges => write!(f, "byteranges"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_displays_byteranges() {
+        let parsed: MultipartType = "ByteRanges".parse().unwrap();
+        assert_eq!(parsed, MultipartType::ByteRanges);
+        assert_eq!(parsed.to_string(), "byteranges");
+    }
+}
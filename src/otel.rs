@@ -0,0 +1,172 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+    time::SystemTime,
+};
+
+use futures_core::Stream;
+use opentelemetry::{
+    trace::{Span, Status, Tracer},
+    KeyValue,
+};
+
+use crate::{
+    error::MultipartError,
+    reader::{MultipartItem, MultipartReader},
+};
+
+/// Wraps a reader and emits an OpenTelemetry span per part.
+///
+/// Each `multipart.part` span covers the time from requesting the part until it was read and
+/// carries the attributes `multipart.part.name`, `multipart.part.filename`,
+/// `multipart.part.content_type`, `multipart.part.size` and `multipart.part.outcome`
+/// (`ok` or `error`). Failed parts also get an error status with the message.
+pub struct TracedReader<'a, E, T> {
+    reader: MultipartReader<'a, E>,
+    tracer: T,
+    /// When the part currently being read was requested
+    started: Option<SystemTime>,
+}
+
+impl<'a, E, T: Tracer> TracedReader<'a, E, T> {
+    pub fn new(reader: MultipartReader<'a, E>, tracer: T) -> TracedReader<'a, E, T> {
+        TracedReader {
+            reader,
+            tracer,
+            started: None,
+        }
+    }
+
+    pub fn get_ref(&self) -> &MultipartReader<'a, E> {
+        &self.reader
+    }
+
+    pub fn into_inner(self) -> MultipartReader<'a, E> {
+        self.reader
+    }
+
+    fn record(&self, started: SystemTime, item: &Result<MultipartItem, MultipartError>) {
+        let mut attributes = vec![];
+        match item {
+            Ok(item) => {
+                if let Some(name) = item.name() {
+                    attributes.push(KeyValue::new("multipart.part.name", name.to_string()));
+                }
+                if let Some(filename) = item.filename() {
+                    attributes.push(KeyValue::new(
+                        "multipart.part.filename",
+                        filename.to_string(),
+                    ));
+                }
+                if let Some(content_type) = item.header("content-type") {
+                    attributes.push(KeyValue::new(
+                        "multipart.part.content_type",
+                        content_type.trim().to_string(),
+                    ));
+                }
+                attributes.push(KeyValue::new("multipart.part.size", item.data.len() as i64));
+                attributes.push(KeyValue::new("multipart.part.outcome", "ok"));
+            }
+            Err(_) => attributes.push(KeyValue::new("multipart.part.outcome", "error")),
+        }
+
+        let mut span = self
+            .tracer
+            .span_builder("multipart.part")
+            .with_start_time(started)
+            .with_attributes(attributes)
+            .start(&self.tracer);
+        if let Err(e) = item {
+            span.set_status(Status::error(e.to_string()));
+        }
+        span.end();
+    }
+}
+
+impl<'a, E, T: Tracer + Unpin> Stream for TracedReader<'a, E, T> {
+    type Item = Result<MultipartItem, MultipartError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let started = *this.started.get_or_insert_with(SystemTime::now);
+
+        let item = Pin::new(&mut this.reader).poll_next(cx);
+        if let Poll::Ready(item) = &item {
+            this.started = None;
+            if let Some(item) = item {
+                this.record(started, item);
+            }
+        }
+        item
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::{FutureExt, StreamExt};
+    use opentelemetry::{trace::TracerProvider, Value};
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider};
+
+    use super::*;
+    use crate::multipart_type::MultipartType;
+
+    // The simple exporter blocks on its own executor, so the reader is polled without one
+    #[test]
+    fn emits_span_per_part() {
+        let data = b"--boundary\r
+Content-Disposition: form-data; name=\"file1\"; filename=\"a.txt\"\r
+Content-Type: text/plain\r
+\r
+Content of a.txt.\r
+--boundary\r
+Content-Disposition: form-data; name=\"text\"\r
+\r
+\xff\r
+--boundary--\r\n";
+        let reader = MultipartReader::<std::io::Error>::from_data_with_boundary_and_type(
+            data,
+            "boundary",
+            MultipartType::FormData,
+        )
+        .unwrap()
+        .with_text_policy(crate::text::TextPolicy::Strict);
+
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let mut traced = TracedReader::new(reader, provider.tracer("test"));
+        while let Some(Some(_)) = traced.next().now_or_never() {}
+
+        let spans = exporter.get_finished_spans().unwrap();
+        assert_eq!(spans.len(), 2);
+        let attribute = |idx: usize, key: &str| {
+            spans[idx]
+                .attributes
+                .iter()
+                .find(|kv| kv.key.as_str() == key)
+                .map(|kv| kv.value.clone())
+        };
+
+        assert_eq!(spans[0].name, "multipart.part");
+        assert_eq!(
+            attribute(0, "multipart.part.filename"),
+            Some(Value::from("a.txt"))
+        );
+        assert_eq!(
+            attribute(0, "multipart.part.content_type"),
+            Some(Value::from("text/plain"))
+        );
+        assert_eq!(attribute(0, "multipart.part.size"), Some(Value::I64(17)));
+        assert_eq!(
+            attribute(0, "multipart.part.outcome"),
+            Some(Value::from("ok"))
+        );
+
+        assert_eq!(
+            attribute(1, "multipart.part.outcome"),
+            Some(Value::from("error"))
+        );
+        assert!(matches!(spans[1].status, Status::Error { .. }));
+    }
+}
@@ -0,0 +1,155 @@
+use std::io::Read;
+
+use bytes::{Buf, Bytes, BytesMut};
+
+use crate::{compression, error::MultipartError, reader::MultipartItem, writer::Chunk};
+
+/// Common view of parsed items and parts to be written.
+///
+/// Lets middleware like checksums, redaction or inspection be written once and applied on
+/// both the reading and the writing side.
+pub trait PartLike {
+    fn headers(&self) -> &[(String, String)];
+
+    fn headers_mut(&mut self) -> &mut Vec<(String, String)>;
+
+    /// The body, if it is held in memory
+    fn body(&self) -> Option<&[u8]>;
+
+    /// Replace the body
+    fn set_body(&mut self, body: Bytes);
+
+    /// Returns the value of the first header matching `name` (case-insensitive)
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers()
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// A part to be appended to a `MultipartWriter`
+pub struct MultipartPart {
+    pub headers: Vec<(String, String)>,
+    pub(crate) body: Chunk,
+}
+
+impl MultipartPart {
+    pub fn new<B: Into<Bytes>>(data: B) -> MultipartPart {
+        MultipartPart {
+            headers: vec![],
+            body: Chunk::Data(data.into()),
+        }
+    }
+
+    /// A part whose body is read from `source` while the document is emitted
+    pub fn from_reader<R: Read + Send + 'static>(source: R) -> MultipartPart {
+        MultipartPart {
+            headers: vec![],
+            body: Chunk::Source(Box::new(source)),
+        }
+    }
+
+    pub fn with_header(mut self, key: &str, value: &str) -> Self {
+        self.headers.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Compress the body while it is emitted and declare it with a Content-Encoding header.
+    ///
+    /// Supports `gzip`, `deflate`, `zstd` and `br` (with the features `gzip`, `deflate`, `zstd`
+    /// and `brotli`) and `identity`, other encodings fail with `MultipartError::UnsupportedEncoding`.
+    pub fn with_encoding(mut self, encoding: &str) -> Result<Self, MultipartError> {
+        let source: Box<dyn Read + Send> = match self.body {
+            Chunk::Data(data) => Box::new(data.reader()),
+            Chunk::Source(source) => source,
+        };
+
+        self.body = Chunk::Source(compression::encoder(encoding, source)?);
+        Ok(self.with_header("Content-Encoding", encoding))
+    }
+}
+
+impl PartLike for MultipartPart {
+    fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    fn headers_mut(&mut self) -> &mut Vec<(String, String)> {
+        &mut self.headers
+    }
+
+    fn body(&self) -> Option<&[u8]> {
+        match &self.body {
+            Chunk::Data(data) => Some(data),
+            Chunk::Source(_) => None,
+        }
+    }
+
+    fn set_body(&mut self, body: Bytes) {
+        self.body = Chunk::Data(body);
+    }
+}
+
+impl PartLike for MultipartItem {
+    fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    fn headers_mut(&mut self) -> &mut Vec<(String, String)> {
+        &mut self.headers
+    }
+
+    fn body(&self) -> Option<&[u8]> {
+        Some(&self.data)
+    }
+
+    fn set_body(&mut self, body: Bytes) {
+        self.data = BytesMut::from(&body[..]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{multipart_type::MultipartType, writer::MultipartWriter};
+
+    fn redact<P: PartLike>(part: &mut P) {
+        part.headers_mut()
+            .retain(|(key, _)| !key.eq_ignore_ascii_case("authorization"));
+        if part.header("content-type") == Some("application/x-secret") {
+            part.set_body(Bytes::from_static(b"<redacted>"));
+        }
+    }
+
+    #[test]
+    fn middleware_applies_to_both_sides() {
+        let mut item = MultipartItem {
+            headers: vec![
+                ("Authorization".to_string(), "Bearer 123".to_string()),
+                (
+                    "Content-Type".to_string(),
+                    "application/x-secret".to_string(),
+                ),
+            ],
+            data: BytesMut::from(&b"password"[..]),
+        };
+        redact(&mut item);
+        assert_eq!(item.headers.len(), 1);
+        assert_eq!(&item.data[..], b"<redacted>");
+
+        let mut part = MultipartPart::new("password")
+            .with_header("authorization", "Bearer 123")
+            .with_header("Content-Type", "application/x-secret");
+        redact(&mut part);
+        assert_eq!(part.body(), Some(&b"<redacted>"[..]));
+
+        let mut writer =
+            MultipartWriter::new_with_boundary("boundary", MultipartType::Mixed).unwrap();
+        writer.append_part(part).unwrap();
+        assert_eq!(
+            writer.finish().unwrap(),
+            &b"--boundary\r\nContent-Type: application/x-secret\r\n\r\n<redacted>\r\n--boundary--\r\n"[..]
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::{Buf, BytesMut};
+use futures_util::io::AsyncWrite;
+
+use crate::{error::MultipartError, reader::MultipartItem};
+
+/// Copies parts selected by a predicate verbatim to a sink
+pub(crate) struct Passthrough<'a> {
+    /// Boundary of the source document, which frames the parts in the sink too
+    boundary: String,
+    predicate: Box<dyn FnMut(&MultipartItem) -> bool + 'a>,
+    sink: Pin<Box<dyn AsyncWrite + 'a>>,
+    /// Raw header lines of the current part, until it is known whether it is passed through
+    head: BytesMut,
+    /// Data which was not written to the sink yet
+    out: BytesMut,
+    /// Whether the current part is passed through
+    active: bool,
+    /// Whether the sink must be flushed once `out` was written
+    needs_flush: bool,
+    /// Whether a part was written, which the closing delimiter must follow
+    has_parts: bool,
+    /// Whether the closing delimiter was written
+    closed: bool,
+}
+
+impl<'a> Passthrough<'a> {
+    pub(crate) fn new<P, W>(boundary: &str, predicate: P, sink: W) -> Passthrough<'a>
+    where
+        P: FnMut(&MultipartItem) -> bool + 'a,
+        W: AsyncWrite + 'a,
+    {
+        Passthrough {
+            boundary: boundary.to_string(),
+            predicate: Box::new(predicate),
+            sink: Box::pin(sink),
+            head: BytesMut::new(),
+            out: BytesMut::new(),
+            active: false,
+            needs_flush: false,
+            has_parts: false,
+            closed: false,
+        }
+    }
+
+    /// Remember a header line of the current part, including its line break
+    pub(crate) fn push_head(&mut self, line: &[u8]) {
+        self.head.extend_from_slice(line);
+    }
+
+    /// Decide whether the part whose headers ended with `line` is passed through
+    pub(crate) fn start_part(&mut self, item: &MultipartItem, line: &[u8]) {
+        self.active = (self.predicate)(item);
+        if self.active {
+            self.has_parts = true;
+            self.out.extend_from_slice(b"--");
+            self.out.extend_from_slice(self.boundary.as_bytes());
+            self.out.extend_from_slice(b"\r\n");
+            self.out.extend_from_slice(&self.head);
+            self.out.extend_from_slice(line);
+        }
+        self.head.clear();
+    }
+
+    /// Forget the headers of a part which is dropped anyway
+    pub(crate) fn skip_part(&mut self) {
+        self.head.clear();
+    }
+
+    pub(crate) fn push_body(&mut self, data: &[u8]) {
+        self.out.extend_from_slice(data);
+    }
+
+    pub(crate) fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub(crate) fn finish_part(&mut self) {
+        // The line break in front of the next delimiter
+        self.out.extend_from_slice(b"\r\n");
+        self.active = false;
+        self.needs_flush = true;
+    }
+
+    /// Write the closing delimiter after the last part, once
+    pub(crate) fn finish_document(&mut self) {
+        if self.has_parts && !self.closed {
+            self.out.extend_from_slice(b"--");
+            self.out.extend_from_slice(self.boundary.as_bytes());
+            self.out.extend_from_slice(b"--\r\n");
+            self.needs_flush = true;
+        }
+        self.closed = true;
+    }
+
+    /// Write all pending data to the sink
+    pub(crate) fn poll_write_out(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), MultipartError>> {
+        while !self.out.is_empty() {
+            match self.sink.as_mut().poll_write(cx, &self.out) {
+                Poll::Ready(Ok(0)) | Poll::Ready(Err(_)) => {
+                    return Poll::Ready(Err(MultipartError::WritingDataFailed))
+                }
+                Poll::Ready(Ok(len)) => self.out.advance(len),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        if self.needs_flush {
+            match self.sink.as_mut().poll_flush(cx) {
+                Poll::Ready(Ok(())) => self.needs_flush = false,
+                Poll::Ready(Err(_)) => return Poll::Ready(Err(MultipartError::WritingDataFailed)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use crate::{multipart_type::MultipartType, reader::MultipartReader};
+
+    #[futures_test::test]
+    async fn passes_selected_parts_through() {
+        let data = b"--boundary\r
+Content-Disposition: form-data; name=\"meta\"\r
+\r
+{\"id\": 1}\r
+--boundary\r
+Content-Disposition: form-data; name=\"file\"; filename=\"a.bin\"\r
+Content-Type:  application/octet-stream\r
+\r
+\x00\x01\r
+\r
+--boundary\r
+Content-Disposition: form-data; name=\"note\"\r
+\r
+done\r
+--boundary--\r\n";
+        let mut raw = vec![];
+        let reader = MultipartReader::<std::io::Error>::from_data_with_boundary_and_type(
+            data,
+            "boundary",
+            MultipartType::FormData,
+        )
+        .unwrap()
+        .with_passthrough(|item| item.filename().is_some(), &mut raw);
+
+        let names: Vec<_> = reader
+            .map(|item| item.unwrap().name().unwrap().to_string())
+            .collect()
+            .await;
+        assert_eq!(names, vec!["meta", "note"]);
+        assert_eq!(
+            raw,
+            &b"--boundary\r
+Content-Disposition: form-data; name=\"file\"; filename=\"a.bin\"\r
+Content-Type:  application/octet-stream\r
+\r
+\x00\x01\r
+\r
+--boundary--\r
+"[..]
+        );
+    }
+
+    #[futures_test::test]
+    async fn passes_several_parts_through() {
+        let data = b"--boundary\r
+X: 1\r
+\r
+one\r
+--boundary\r
+\r
+kept\r
+--boundary\r
+X: 1\r
+\r
+two\r\n\r
+--boundary--\r\n";
+        let mut raw = vec![];
+        let reader = MultipartReader::<std::io::Error>::from_data_with_boundary_and_type(
+            data,
+            "boundary",
+            MultipartType::Mixed,
+        )
+        .unwrap()
+        .with_passthrough(|item| item.header("x").is_some(), &mut raw);
+        assert_eq!(reader.count().await, 1);
+
+        let mut passed = MultipartReader::<std::io::Error>::from_data_with_boundary_and_type(
+            &raw,
+            "boundary",
+            MultipartType::Mixed,
+        )
+        .unwrap();
+        let mut bodies = vec![];
+        while let Some(item) = passed.next().await {
+            bodies.push(item.unwrap().data.to_vec());
+        }
+        assert_eq!(bodies, vec![b"one".to_vec(), b"two\r\n".to_vec()]);
+        assert!(passed.finished_cleanly());
+    }
+}
@@ -0,0 +1,117 @@
+use std::time::{Duration, Instant};
+
+use crate::error::MultipartError;
+
+/// Limits for the data in front of the first boundary.
+///
+/// Protect endpoints expecting multipart bodies against clients which send an endless body
+/// of something else, which would otherwise be skipped forever.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PreambleLimits {
+    /// Maximum number of bytes up to and including the first boundary line
+    pub max_bytes: u64,
+
+    /// Maximum time from the first poll of the reader until the first boundary was found.
+    ///
+    /// This is checked when data arrives, so a source which sends nothing at all must be
+    /// guarded with a timeout of the runtime.
+    pub max_duration: Option<Duration>,
+}
+
+impl Default for PreambleLimits {
+    fn default() -> Self {
+        PreambleLimits {
+            max_bytes: 64 * 1024,
+            max_duration: None,
+        }
+    }
+}
+
+/// Tracks the preamble of a reader against its limits
+#[derive(Debug, Clone)]
+pub(crate) struct PreambleGuard {
+    limits: PreambleLimits,
+    /// Bytes of preamble lines which were skipped already
+    skipped: u64,
+    started: Option<Instant>,
+}
+
+impl PreambleGuard {
+    pub(crate) fn new(limits: PreambleLimits) -> PreambleGuard {
+        PreambleGuard {
+            limits,
+            skipped: 0,
+            started: None,
+        }
+    }
+
+    /// Skip a preamble line of `len` bytes, failing once the preamble is too long
+    pub(crate) fn skip(&mut self, len: usize) -> Result<(), MultipartError> {
+        self.skipped += len as u64;
+        self.check(0)
+    }
+
+    /// Fail if the preamble including the `buffered` bytes of the current line is too long
+    pub(crate) fn check(&mut self, buffered: usize) -> Result<(), MultipartError> {
+        let started = *self.started.get_or_insert_with(Instant::now);
+
+        let too_long = self.skipped + buffered as u64 > self.limits.max_bytes;
+        let too_slow = self
+            .limits
+            .max_duration
+            .is_some_and(|max| started.elapsed() > max);
+        if too_long || too_slow {
+            return Err(MultipartError::PreambleTooLong);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use super::*;
+    use crate::{multipart_type::MultipartType, reader::MultipartReader};
+
+    fn limited(data: &[u8], max_bytes: u64) -> MultipartReader<'static, std::io::Error> {
+        MultipartReader::from_data_with_boundary_and_type(data, "boundary", MultipartType::Mixed)
+            .unwrap()
+            .with_preamble_limits(PreambleLimits {
+                max_bytes,
+                max_duration: None,
+            })
+    }
+
+    #[futures_test::test]
+    async fn limits_bytes_before_first_boundary() {
+        let data = b"preamble\r\n--boundary\r\n\r\nbody\r\n--boundary--\r\n";
+
+        let mut reader = limited(data, 22);
+        assert_eq!(&reader.next().await.unwrap().unwrap().data[..], b"body");
+
+        let endless = vec![b'x'; 1024];
+        let mut reader = limited(&endless, 512);
+        assert!(matches!(
+            reader.next().await,
+            Some(Err(MultipartError::PreambleTooLong))
+        ));
+        assert!(reader.next().await.is_none());
+    }
+
+    #[futures_test::test]
+    async fn limits_preamble_in_same_chunk_as_boundary() {
+        let mut data = b"x\r\n".repeat(10_000);
+        data.extend_from_slice(b"--boundary\r\n\r\nbody\r\n--boundary--\r\n");
+
+        let mut reader = limited(&data, 100);
+        assert!(matches!(
+            reader.next().await,
+            Some(Err(MultipartError::PreambleTooLong))
+        ));
+
+        // The boundary line itself counts, too
+        let data = b"--boundary\r\n\r\nbody\r\n--boundary--\r\n";
+        assert!(limited(data, 11).next().await.unwrap().is_err());
+    }
+}
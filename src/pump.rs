@@ -0,0 +1,262 @@
+use bytes::{BufMut, BytesMut};
+use futures_util::{io::AsyncWrite, stream, AsyncWriteExt, StreamExt};
+
+use crate::{
+    error::MultipartError,
+    reader::{MultipartItem, MultipartReader},
+    writer::MultipartWriter,
+};
+
+/// Statistics of a relayed part
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartStats {
+    pub name: Option<String>,
+
+    /// Size of the body in bytes
+    pub size: u64,
+}
+
+/// Statistics of a `pump` run
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PumpStats {
+    pub parts: Vec<PartStats>,
+
+    /// Total number of bytes written to the sink
+    pub bytes_written: u64,
+}
+
+/// Relay all parts from `reader` through `writer` into `sink`, e.g. in a proxy.
+///
+/// Headers and bodies are passed on unchanged: transformations of the reader like decompression
+/// or text policies are disabled, its limits still apply. Parts are re-framed with the
+/// boundary of the writer, so the Content-Type sent downstream must be `writer.content_type()`.
+///
+/// Every part is held in memory until it was read completely and is then written to the sink,
+/// so a part larger than `max_part_size` bytes aborts the pump with
+/// `MultipartError::BufferTooSmall`. The first error of the reader, the writer or the sink
+/// aborts the pump and is returned, as is `MultipartError::IncompleteDocument` if the source
+/// ends without the terminating boundary.
+/// In both cases, and when the future is dropped, the sink is left with an unterminated
+/// document, so the downstream peer can tell the relay was cut short.
+pub async fn pump<'a, E, W>(
+    mut reader: MultipartReader<'a, E>,
+    mut writer: MultipartWriter,
+    sink: &mut W,
+    max_part_size: usize,
+) -> Result<PumpStats, MultipartError>
+where
+    W: AsyncWrite + Unpin,
+{
+    reader.disable_transformations();
+    let mut stats = PumpStats::default();
+
+    while let Some(part) = read_part(&mut reader, max_part_size).await {
+        relay(&mut writer, sink, &mut stats, part?).await?;
+    }
+
+    if !reader.finished_cleanly() {
+        return Err(MultipartError::IncompleteDocument);
+    }
+
+    stats.bytes_written += write(sink, &writer.finish()?).await?;
+    sink.flush()
+        .await
+        .map_err(|_| MultipartError::WritingDataFailed)?;
+    Ok(stats)
+}
+
+/// Relay the parts of several documents into `sink` as one document, e.g. in a gateway which
+/// aggregates multipart responses of several backends.
+///
+/// Parts are written in the order in which they become available, so a slow source does not
+/// hold back the others. Parts are relayed like with `pump`, so one part of every source may
+/// be held in memory at a time, and the first error of any source, including
+/// `MultipartError::IncompleteDocument`, aborts the merge.
+pub async fn merge<'a, E: 'a, W>(
+    readers: Vec<MultipartReader<'a, E>>,
+    mut writer: MultipartWriter,
+    sink: &mut W,
+    max_part_size: usize,
+) -> Result<PumpStats, MultipartError>
+where
+    W: AsyncWrite + Unpin,
+{
+    let sources = readers.into_iter().map(|mut reader| {
+        reader.disable_transformations();
+        stream::unfold(Some(reader), |reader| async move {
+            let mut reader = reader?;
+            match read_part(&mut reader, max_part_size).await {
+                Some(part) => Some((part, Some(reader))),
+                None if reader.finished_cleanly() => None,
+                None => Some((Err(MultipartError::IncompleteDocument), None)),
+            }
+        })
+        .boxed_local()
+    });
+    let mut parts = stream::select_all(sources);
+    let mut stats = PumpStats::default();
+
+    while let Some(part) = parts.next().await {
+        relay(&mut writer, sink, &mut stats, part?).await?;
+    }
+
+    stats.bytes_written += write(sink, &writer.finish()?).await?;
+    sink.flush()
+        .await
+        .map_err(|_| MultipartError::WritingDataFailed)?;
+    Ok(stats)
+}
+
+/// Read the next part with a body of at most `max_part_size` bytes
+async fn read_part<E>(
+    reader: &mut MultipartReader<'_, E>,
+    max_part_size: usize,
+) -> Option<Result<MultipartItem, MultipartError>> {
+    let mut data = BytesMut::new();
+    let mut limited = (&mut data).limit(max_part_size);
+    let part = reader.read_part_into(&mut limited).await?;
+
+    Some(part.map(|part| MultipartItem {
+        headers: part.headers,
+        data,
+    }))
+}
+
+/// Append a part to `writer` and write out the chunks it produced
+async fn relay<W: AsyncWrite + Unpin>(
+    writer: &mut MultipartWriter,
+    sink: &mut W,
+    stats: &mut PumpStats,
+    item: MultipartItem,
+) -> Result<(), MultipartError> {
+    stats.parts.push(PartStats {
+        name: item.name().map(str::to_string),
+        size: item.data.len() as u64,
+    });
+
+    writer.append(&item.headers, item.data.freeze())?;
+    while let Some(chunk) = writer.take_chunk()? {
+        stats.bytes_written += write(sink, &chunk).await?;
+    }
+    Ok(())
+}
+
+async fn write<W: AsyncWrite + Unpin>(sink: &mut W, data: &[u8]) -> Result<u64, MultipartError> {
+    sink.write_all(data)
+        .await
+        .map_err(|_| MultipartError::WritingDataFailed)?;
+    Ok(data.len() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multipart_type::MultipartType;
+
+    fn reader(data: &[u8]) -> MultipartReader<'static, std::io::Error> {
+        MultipartReader::from_data_with_boundary_and_type(data, "upstream", MultipartType::FormData)
+            .unwrap()
+    }
+
+    #[futures_test::test]
+    async fn relays_parts() {
+        let data = b"--upstream\r
+Content-Disposition: form-data; name=\"text\"\r
+\r
+text default\r
+--upstream\r
+Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r
+Content-Encoding: gzip\r
+\r
+not really gzip\r
+--upstream--\r\n";
+        let writer =
+            MultipartWriter::new_with_boundary("downstream", MultipartType::FormData).unwrap();
+
+        let mut sink = vec![];
+        let stats = pump(reader(data), writer, &mut sink, 1024).await.unwrap();
+
+        let relayed = String::from_utf8(data.to_vec())
+            .unwrap()
+            .replace("upstream", "downstream");
+        assert_eq!(sink, relayed.as_bytes());
+        assert_eq!(stats.bytes_written, sink.len() as u64);
+        assert_eq!(
+            stats.parts,
+            vec![
+                PartStats {
+                    name: Some("text".to_string()),
+                    size: 12
+                },
+                PartStats {
+                    name: Some("file".to_string()),
+                    size: 15
+                }
+            ]
+        );
+    }
+
+    #[futures_test::test]
+    async fn merges_sources() {
+        let first = reader(b"--upstream\r\n\r\na1\r\n--upstream\r\n\r\na2\r\n--upstream--\r\n");
+        let second = reader(b"--upstream\r\n\r\nb1\r\n--upstream--\r\n");
+        let writer =
+            MultipartWriter::new_with_boundary("downstream", MultipartType::Mixed).unwrap();
+
+        let mut sink = vec![];
+        let stats = merge(vec![first, second], writer, &mut sink, 1024)
+            .await
+            .unwrap();
+        assert_eq!(stats.parts.len(), 3);
+
+        let mut merged = MultipartReader::<std::io::Error>::from_data_with_boundary_and_type(
+            &sink,
+            "downstream",
+            MultipartType::Mixed,
+        )
+        .unwrap();
+        let mut bodies = vec![];
+        while let Some(item) = merged.next().await {
+            bodies.push(item.unwrap().data.to_vec());
+        }
+        bodies.sort();
+        assert_eq!(bodies, vec![b"a1".to_vec(), b"a2".to_vec(), b"b1".to_vec()]);
+        assert!(merged.finished_cleanly());
+
+        let truncated = reader(b"--upstream\r\n\r\ntrunc");
+        let writer =
+            MultipartWriter::new_with_boundary("downstream", MultipartType::Mixed).unwrap();
+        assert!(matches!(
+            merge(vec![truncated], writer, &mut vec![], 1024).await,
+            Err(MultipartError::IncompleteDocument)
+        ));
+    }
+
+    #[futures_test::test]
+    async fn reports_truncated_source() {
+        let data = b"--upstream\r\n\r\ncomplete\r\n--upstream\r\n\r\ntrunc";
+        let writer =
+            MultipartWriter::new_with_boundary("downstream", MultipartType::Mixed).unwrap();
+
+        let mut sink = vec![];
+        assert!(matches!(
+            pump(reader(data), writer, &mut sink, 1024).await,
+            Err(MultipartError::IncompleteDocument)
+        ));
+        assert_eq!(sink, b"--downstream\r\n\r\ncomplete\r\n");
+    }
+
+    #[futures_test::test]
+    async fn limits_part_size() {
+        let data = b"--upstream\r\n\r\nsmall\r\n--upstream\r\n\r\ntoo large\r\n--upstream--\r\n";
+        let writer =
+            MultipartWriter::new_with_boundary("downstream", MultipartType::Mixed).unwrap();
+
+        let mut sink = vec![];
+        assert!(matches!(
+            pump(reader(data), writer, &mut sink, 8).await,
+            Err(MultipartError::BufferTooSmall)
+        ));
+        assert_eq!(sink, b"--downstream\r\n\r\nsmall\r\n");
+    }
+}
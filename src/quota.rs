@@ -0,0 +1,170 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_core::future::LocalBoxFuture;
+
+use crate::error::MultipartError;
+
+/// Running totals of a reader, passed to a `QuotaCheck`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct QuotaUsage {
+    /// Bytes received from the source
+    pub bytes: u64,
+
+    /// Parts read completely
+    pub parts: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuotaDecision {
+    Continue,
+
+    /// Abort parsing with `MultipartError::QuotaExceeded`
+    Stop,
+}
+
+/// Asks an external service whether a reader may continue, e.g. per user or tenant.
+///
+/// The context of the caller (user id, client handle, ...) is carried by the implementation.
+/// Closures returning a boxed future implement this trait as well.
+pub trait QuotaCheck {
+    fn check(&mut self, usage: QuotaUsage) -> LocalBoxFuture<'static, QuotaDecision>;
+}
+
+impl<F> QuotaCheck for F
+where
+    F: FnMut(QuotaUsage) -> LocalBoxFuture<'static, QuotaDecision>,
+{
+    fn check(&mut self, usage: QuotaUsage) -> LocalBoxFuture<'static, QuotaDecision> {
+        self(usage)
+    }
+}
+
+/// Tracks the usage of a reader and the decision currently awaited
+pub(crate) struct QuotaState<'a> {
+    check: Box<dyn QuotaCheck + 'a>,
+    /// Minimum number of bytes between two checks
+    interval: u64,
+    usage: QuotaUsage,
+    last_checked: u64,
+    pending: Option<LocalBoxFuture<'static, QuotaDecision>>,
+}
+
+impl<'a> QuotaState<'a> {
+    pub(crate) fn new<Q: QuotaCheck + 'a>(check: Q, interval: u64) -> QuotaState<'a> {
+        QuotaState {
+            check: Box::new(check),
+            interval,
+            usage: QuotaUsage::default(),
+            last_checked: 0,
+            pending: None,
+        }
+    }
+
+    pub(crate) fn record_bytes(&mut self, len: usize) {
+        self.usage.bytes += len as u64;
+        if self.pending.is_none() && self.usage.bytes - self.last_checked >= self.interval {
+            self.last_checked = self.usage.bytes;
+            self.pending = Some(self.check.check(self.usage));
+        }
+    }
+
+    pub(crate) fn record_part(&mut self) {
+        self.usage.parts += 1;
+    }
+
+    /// Wait for the pending decision, if any
+    pub(crate) fn poll_decision(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), MultipartError>> {
+        let Some(pending) = &mut self.pending else {
+            return Poll::Ready(Ok(()));
+        };
+
+        let decision = match Pin::new(pending).poll(cx) {
+            Poll::Ready(decision) => decision,
+            Poll::Pending => return Poll::Pending,
+        };
+        self.pending = None;
+        match decision {
+            QuotaDecision::Continue => Poll::Ready(Ok(())),
+            QuotaDecision::Stop => Poll::Ready(Err(MultipartError::QuotaExceeded)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use bytes::Bytes;
+    use futures_util::{FutureExt, StreamExt};
+
+    use super::*;
+    use crate::{multipart_type::MultipartType, reader::MultipartReader};
+
+    #[futures_test::test]
+    async fn aborts_when_quota_service_says_stop() {
+        let chunks = [
+            &b"--boundary\r\n\r\nfirst\r\n"[..],
+            b"--boundary\r\n\r\nsecond\r\n",
+            b"--boundary\r\n\r\nthird\r\n",
+            b"--boundary--\r\n",
+        ]
+        .map(|chunk| Ok::<_, std::io::Error>(Bytes::from_static(chunk)));
+
+        let seen = Rc::new(RefCell::new(vec![]));
+        let check = {
+            let seen = seen.clone();
+            move |usage: QuotaUsage| {
+                seen.borrow_mut().push(usage);
+                async move {
+                    // Allow the first two chunks only
+                    if usage.bytes > 50 {
+                        QuotaDecision::Stop
+                    } else {
+                        QuotaDecision::Continue
+                    }
+                }
+                .boxed_local()
+            }
+        };
+
+        let mut reader = MultipartReader::from_stream_with_boundary_and_type(
+            futures_util::stream::iter(chunks),
+            "boundary",
+            MultipartType::Mixed,
+        )
+        .unwrap()
+        .with_quota_check(check, 1);
+
+        assert_eq!(&reader.next().await.unwrap().unwrap().data[..], b"first");
+        assert!(matches!(
+            reader.next().await,
+            Some(Err(MultipartError::QuotaExceeded))
+        ));
+        assert!(reader.next().await.is_none());
+
+        assert_eq!(
+            *seen.borrow(),
+            vec![
+                QuotaUsage {
+                    bytes: 21,
+                    parts: 0
+                },
+                QuotaUsage {
+                    bytes: 43,
+                    parts: 0
+                },
+                QuotaUsage {
+                    bytes: 64,
+                    parts: 1
+                }
+            ]
+        );
+    }
+}
@@ -1,17 +1,42 @@
 use std::{
+    future::Future,
     pin::Pin,
     str,
     task::{Context, Poll},
 };
 
-use bytes::{Buf, Bytes, BytesMut};
+use bytes::{BufMut, Bytes, BytesMut};
 use futures_core::{stream::LocalBoxStream, Stream};
-use futures_util::StreamExt;
+use futures_util::{
+    io::{AsyncBufRead, AsyncWrite},
+    StreamExt,
+};
+use memchr::memmem;
 
-use crate::{error::MultipartError, multipart_type::MultipartType};
+#[cfg(feature = "serde")]
+use crate::state::{self, PendingPart, ReaderState};
+use crate::{
+    codec::CodecRegistry,
+    compat::CompatProfile,
+    compression::{self, DecompressionLimits},
+    content_filter::{ContentTypeFilter, ContentTypePolicy},
+    dedup::PartDeduplicator,
+    describe::{self, PartSummary},
+    error::MultipartError,
+    fn_stream::{ChunkSender, FnStream},
+    line_buffer::LineBuffer,
+    media_type,
+    multipart_type::MultipartType,
+    passthrough::Passthrough,
+    preamble::{PreambleGuard, PreambleLimits},
+    quota::{QuotaCheck, QuotaState},
+    text::{self, TextPolicy},
+    writer,
+};
 
-#[derive(PartialEq, Debug)]
-enum InnerState {
+#[derive(PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) enum InnerState {
     /// Stream eof
     Eof,
 
@@ -25,6 +50,22 @@ enum InnerState {
     Headers,
 }
 
+/// Progress on the body of the current part
+#[derive(Default)]
+struct BodyState {
+    /// Number of body bytes written so far
+    len: u64,
+
+    /// Whether the line break of the last line is held back, as it may belong to a delimiter
+    held_crlf: bool,
+
+    /// Whether the part is a duplicate whose body is dropped
+    discard: bool,
+
+    /// Whether the buffer starts in the middle of a body line, which can't be a delimiter
+    mid_line: bool,
+}
+
 pub struct MultipartItem {
     /// Headers
     pub headers: Vec<(String, String)>,
@@ -33,14 +74,170 @@ pub struct MultipartItem {
     pub data: BytesMut,
 }
 
+impl MultipartItem {
+    /// Returns the value of the first header matching `name` (case-insensitive)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The `name` parameter of the Content-Disposition header
+    pub fn name(&self) -> Option<&str> {
+        header_param(self.header("content-disposition")?, "name")
+    }
+
+    /// The `filename` parameter of the Content-Disposition header
+    pub fn filename(&self) -> Option<&str> {
+        header_param(self.header("content-disposition")?, "filename")
+    }
+
+    /// Whether this is a plain form field, i.e. neither a file upload nor a non-text part
+    pub fn is_text_field(&self) -> bool {
+        let is_text = match self.header("content-type") {
+            Some(content_type) => content_type
+                .trim_start()
+                .get(..5)
+                .is_some_and(|t| t.eq_ignore_ascii_case("text/")),
+            None => true,
+        };
+
+        is_text && self.filename().is_none()
+    }
+
+    /// The key identifying re-sent copies of this part.
+    ///
+    /// Uses the `Idempotency-Key` header and falls back to `Content-ID`.
+    pub fn idempotency_key(&self) -> Option<&str> {
+        self.header("idempotency-key")
+            .or_else(|| self.header("content-id"))
+            .map(str::trim)
+    }
+}
+
+/// A part whose body was written into a buffer of the caller, see `read_part_into`
+#[derive(Debug)]
+pub struct ExtractedPart {
+    pub headers: Vec<(String, String)>,
+
+    /// Number of body bytes written into the buffer
+    pub len: usize,
+}
+
+/// Find a parameter of a header value like `form-data; name="file"; filename="a.txt"`
+pub(crate) fn header_param<'h>(value: &'h str, name: &str) -> Option<&'h str> {
+    // Split at semicolons which are not part of a quoted string
+    let mut params = vec![];
+    let mut in_quotes = false;
+    let mut start = 0;
+    for (idx, c) in value.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ';' if !in_quotes => {
+                params.push(&value[start..idx]);
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    params.push(&value[start..]);
+
+    // The first entry is the header value itself
+    params.into_iter().skip(1).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        if !key.trim().eq_ignore_ascii_case(name) {
+            return None;
+        }
+
+        let value = value.trim();
+        Some(
+            value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value),
+        )
+    })
+}
+
+#[derive(Clone)]
+struct ReaderOptions {
+    /// Limits for transparent decoding of compressed parts, if enabled
+    decompression: Option<DecompressionLimits>,
+
+    /// How the values of text form fields are validated
+    text_policy: TextPolicy,
+
+    /// Drops parts whose idempotency key was already seen
+    deduplicator: Option<PartDeduplicator>,
+
+    /// Whether a summary of every part is recorded for `describe`
+    record_summaries: bool,
+
+    /// Maximum nesting depth of documents parsed with `nested_reader`
+    max_depth: usize,
+
+    /// Tolerated deviations from the delimiter syntax
+    compat: CompatProfile,
+
+    /// Allow-list of declared part content types, if enabled
+    content_types: Option<ContentTypeFilter>,
+
+    /// Custom codecs decoding the bodies of matching parts
+    codecs: Option<CodecRegistry>,
+}
+
+impl Default for ReaderOptions {
+    fn default() -> Self {
+        ReaderOptions {
+            decompression: None,
+            text_policy: TextPolicy::default(),
+            deduplicator: None,
+            record_summaries: false,
+            max_depth: 8,
+            compat: CompatProfile::STRICT,
+            content_types: None,
+            codecs: None,
+        }
+    }
+}
+
+/// Where the reader gets the document from
+enum Source<'a, E> {
+    Stream(LocalBoxStream<'a, Result<Bytes, E>>),
+
+    /// Body data is emitted straight out of the buffer of the reader
+    BufRead(Pin<Box<dyn AsyncBufRead + 'a>>),
+}
+
 pub struct MultipartReader<'a, E> {
     pub boundary: String,
     pub multipart_type: MultipartType,
     /// Inner state
     state: InnerState,
-    stream: LocalBoxStream<'a, Result<Bytes, E>>,
-    buf: BytesMut,
+    source: Source<'a, E>,
+    buf: LineBuffer,
     pending_item: Option<MultipartItem>,
+    options: ReaderOptions,
+    /// Whether the terminating boundary was seen
+    finished_cleanly: bool,
+    /// Summaries of the parts read so far, if enabled
+    summaries: Vec<PartSummary>,
+    /// Nesting depth, 0 for the outermost document
+    depth: usize,
+    body: BodyState,
+    /// External quota check, if enabled
+    quota: Option<QuotaState<'a>>,
+    /// Limits for the data in front of the first boundary, if enabled
+    preamble: Option<PreambleGuard>,
+    /// Copies selected parts to a sink instead of yielding them, if enabled
+    passthrough: Option<Passthrough<'a>>,
+    /// Whether all remaining parts are dropped, see `drain`
+    draining: bool,
+    /// State to continue `drain` with after a part was rejected by a per-part policy
+    resume: Option<InnerState>,
+    /// Finds `\r\n--boundary` to skip over body data which can't contain a delimiter
+    delimiter: memmem::Finder<'static>,
 }
 
 impl<'a, E> MultipartReader<'a, E> {
@@ -52,14 +249,33 @@ impl<'a, E> MultipartReader<'a, E> {
     where
         S: Stream<Item = Result<Bytes, E>> + 'a,
     {
-        Ok(MultipartReader {
-            stream: stream.boxed_local(),
+        Ok(MultipartReader::new(
+            Source::Stream(stream.boxed_local()),
+            boundary,
+            multipart_type,
+        ))
+    }
+
+    fn new(source: Source<'a, E>, boundary: &str, multipart_type: MultipartType) -> Self {
+        MultipartReader {
+            source,
             boundary: boundary.to_string(),
-            multipart_type: multipart_type,
+            multipart_type,
             state: InnerState::FirstBoundary,
             pending_item: None,
-            buf: BytesMut::new(),
-        })
+            buf: LineBuffer::default(),
+            options: ReaderOptions::default(),
+            finished_cleanly: false,
+            summaries: vec![],
+            depth: 0,
+            body: BodyState::default(),
+            quota: None,
+            preamble: None,
+            passthrough: None,
+            draining: false,
+            resume: None,
+            delimiter: memmem::Finder::new(format!("\r\n--{}", boundary).as_bytes()).into_owned(),
+        }
     }
 
     pub fn from_data_with_boundary_and_type(
@@ -76,7 +292,7 @@ impl<'a, E> MultipartReader<'a, E> {
 
     pub fn from_stream_with_headers<S>(
         stream: S,
-        headers: &Vec<(String, String)>,
+        headers: &[(String, String)],
     ) -> Result<MultipartReader<'a, E>, MultipartError>
     where
         S: Stream<Item = Result<Bytes, E>> + 'a,
@@ -91,38 +307,18 @@ impl<'a, E> MultipartReader<'a, E> {
             return Err(MultipartError::NoContentType);
         }
 
-        let ct = content_type
-            .unwrap()
-            .1
-            .parse::<mime::Mime>()
-            .map_err(|_e| MultipartError::InvalidContentType)?;
-        let boundary = ct
-            .get_param(mime::BOUNDARY)
-            .ok_or(MultipartError::InvalidBoundary)?;
-
-        if ct.type_() != mime::MULTIPART {
-            return Err(MultipartError::InvalidContentType);
-        }
-
-        let multipart_type = ct
-            .subtype()
-            .as_str()
-            .parse::<MultipartType>()
-            .map_err(|_| MultipartError::InvalidMultipartType)?;
+        let (multipart_type, boundary) = media_type::parse_multipart(&content_type.unwrap().1)?;
 
-        Ok(MultipartReader {
-            stream: stream.boxed_local(),
-            boundary: boundary.to_string(),
-            multipart_type: multipart_type,
-            state: InnerState::FirstBoundary,
-            pending_item: None,
-            buf: BytesMut::new(),
-        })
+        MultipartReader::from_stream_with_boundary_and_type(
+            stream,
+            boundary.as_str(),
+            multipart_type,
+        )
     }
 
     pub fn from_data_with_headers(
         data: &[u8],
-        headers: &Vec<(String, String)>,
+        headers: &[(String, String)],
     ) -> Result<MultipartReader<'a, E>, MultipartError>
     where
         E: std::error::Error + 'a,
@@ -131,81 +327,664 @@ impl<'a, E> MultipartReader<'a, E> {
         MultipartReader::from_stream_with_headers(stream, headers)
     }
 
-    fn is_final_boundary(self: &Self, data: &[u8]) -> bool {
-        let boundary = format!("--{}--", self.boundary);
-        data.starts_with(boundary.as_bytes())
+    /// Read from an async generator which yields chunks through a `ChunkSender`.
+    ///
+    /// This avoids implementing `Stream` by hand for custom sources like WebSocket binary
+    /// frames, gRPC chunks or SSH channels:
+    ///
+    /// ```ignore
+    /// let reader = MultipartReader::from_fn_stream(
+    ///     |mut sender| async move {
+    ///         while let Some(frame) = socket.next().await {
+    ///             sender.send(frame.map(|f| f.into_data())).await;
+    ///         }
+    ///     },
+    ///     &headers,
+    /// )?;
+    /// ```
+    pub fn from_fn_stream<F, Fut>(
+        generator: F,
+        headers: &[(String, String)],
+    ) -> Result<MultipartReader<'a, E>, MultipartError>
+    where
+        F: FnOnce(ChunkSender<E>) -> Fut,
+        Fut: Future<Output = ()> + 'a,
+        E: std::error::Error + 'a,
+    {
+        MultipartReader::from_stream_with_headers(FnStream::new(generator), headers)
     }
 
-    // TODO: make this RFC compliant
-    fn is_boundary(self: &Self, data: &[u8]) -> bool {
-        let boundary = format!("--{}", self.boundary);
-        data.starts_with(boundary.as_bytes())
+    /// Transparently decode parts with a gzip or deflate Content-Encoding/Transfer-Encoding.
+    ///
+    /// Decoding is aborted with `MultipartError::DecompressionLimitExceeded` as soon as a
+    /// part grows beyond the configured size or inflation ratio. The codecs are only
+    /// available with the `gzip` and `deflate` features, other encodings are left untouched.
+    pub fn with_decompression(mut self, limits: DecompressionLimits) -> Self {
+        self.options.decompression = Some(limits);
+        self
     }
-}
 
-impl<'a, E> Stream for MultipartReader<'a, E> {
-    type Item = Result<MultipartItem, MultipartError>;
+    /// Choose whether text form fields must be valid UTF-8, are repaired, or are left as raw bytes.
+    ///
+    /// Only applies to `multipart/form-data` fields without a filename and with a text (or no)
+    /// Content-Type.
+    pub fn with_text_policy(mut self, policy: TextPolicy) -> Self {
+        self.options.text_policy = policy;
+        self
+    }
 
-    fn poll_next(self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let this = self.get_mut();
-        let finder = memchr::memmem::Finder::new("\r\n");
+    /// Skip parts whose idempotency key (see `MultipartItem::idempotency_key`) was already
+    /// recorded by the deduplicator, e.g. by the reader of an earlier attempt of a retried
+    /// request. Keys are only recorded once their part was received completely, so parts cut
+    /// short by a dropped connection are read again on the retry. Parts without a key are
+    /// never skipped.
+    pub fn with_deduplicator(mut self, deduplicator: PartDeduplicator) -> Self {
+        self.options.deduplicator = Some(deduplicator);
+        self
+    }
+
+    /// Only accept parts whose declared Content-Type is one of `types`, e.g. `image/png` or
+    /// `image/*`, and reject or skip others per `policy` before their bodies are buffered.
+    ///
+    /// A missing Content-Type header counts as `text/plain` (RFC 2046). Form fields without a
+    /// filename are accepted regardless, as they carry no upload.
+    pub fn with_allowed_content_types(mut self, types: &[&str], policy: ContentTypePolicy) -> Self {
+        self.options.content_types = Some(ContentTypeFilter::new(types, policy));
+        self
+    }
+
+    /// Decode the bodies of parts matching one of the `codecs`, before built-in decompression
+    pub fn with_codecs(mut self, codecs: CodecRegistry) -> Self {
+        self.options.codecs = Some(codecs);
+        self
+    }
+
+    /// Tolerate known deviations from the delimiter syntax of legacy producers
+    pub fn with_compat(mut self, profile: CompatProfile) -> Self {
+        self.options.compat = profile;
+        self
+    }
+
+    /// Consult `check` with the running totals whenever at least `interval` more bytes were
+    /// received, and abort with `MultipartError::QuotaExceeded` once it decides to stop.
+    ///
+    /// No more data is parsed while a decision is pending. Nested readers are not checked
+    /// on their own, as their data is part of the totals of this reader.
+    pub fn with_quota_check<Q: QuotaCheck + 'a>(mut self, check: Q, interval: u64) -> Self {
+        self.quota = Some(QuotaState::new(check, interval));
+        self
+    }
+
+    /// Fail with `MultipartError::PreambleTooLong` if the first boundary is not found within
+    /// the `limits`
+    pub fn with_preamble_limits(mut self, limits: PreambleLimits) -> Self {
+        self.preamble = Some(PreambleGuard::new(limits));
+        self
+    }
+
+    /// Copy parts for which `predicate` returns `true` verbatim to `sink` instead of yielding
+    /// them, while all other parts are parsed as usual.
+    ///
+    /// The predicate sees the headers of a part, whose delimiter, raw header lines, empty line
+    /// and body are written back to back without buffering the part. Once the document ended
+    /// cleanly the closing delimiter follows, so the sink holds a document with the boundary
+    /// of the reader. It stays empty if no part was selected. The sink is flushed after every
+    /// part, poll the reader until it ends to write the last one.
+    pub fn with_passthrough<P, W>(mut self, predicate: P, sink: W) -> Self
+    where
+        P: FnMut(&MultipartItem) -> bool + 'a,
+        W: AsyncWrite + 'a,
+    {
+        self.passthrough = Some(Passthrough::new(&self.boundary, predicate, sink));
+        self
+    }
+
+    /// Limit how deeply documents may be nested when parsed with `nested_reader` (default 8).
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.options.max_depth = max_depth;
+        self
+    }
+
+    /// Parse a part which is a multipart document itself, e.g. `multipart/mixed` inside of
+    /// `multipart/form-data`.
+    ///
+    /// The nested reader inherits the options of this reader. Fails with
+    /// `MultipartError::NestingTooDeep` once the maximum depth would be exceeded, which
+    /// protects against maliciously deeply nested structures.
+    pub fn nested_reader(
+        &self,
+        item: &MultipartItem,
+    ) -> Result<MultipartReader<'a, E>, MultipartError>
+    where
+        E: std::error::Error + 'a,
+    {
+        if self.depth >= self.options.max_depth {
+            return Err(MultipartError::NestingTooDeep);
+        }
+
+        let mut reader = MultipartReader::from_data_with_headers(&item.data, &item.headers)?;
+        reader.options = self.options.clone();
+        reader.depth = self.depth + 1;
+        Ok(reader)
+    }
+
+    /// Snapshot the parser to resume it in another process with `resume_from_stream`.
+    ///
+    /// Options are not part of the state and must be set again on the resumed reader, which
+    /// starts with empty deduplication, summary and quota records. Bodies which were being
+    /// written into a buffer by `read_part_into` are not part of the state either.
+    #[cfg(feature = "serde")]
+    pub fn save_state(&self) -> ReaderState {
+        ReaderState {
+            version: state::STATE_VERSION,
+            boundary: self.boundary.clone(),
+            multipart_type: self.multipart_type.to_string(),
+            phase: self.state,
+            finished_cleanly: self.finished_cleanly,
+            depth: self.depth,
+            buffered: self.buf.to_vec(),
+            pending: self.pending_item.as_ref().map(|item| PendingPart {
+                headers: item.headers.clone(),
+                data: item.data.to_vec(),
+            }),
+            body_len: self.body.len,
+            held_crlf: self.body.held_crlf,
+            discard: self.body.discard,
+            mid_line: self.body.mid_line,
+        }
+    }
+
+    /// Continue parsing a document from a state saved with `save_state`, with `stream`
+    /// delivering the data which followed.
+    #[cfg(feature = "serde")]
+    pub fn resume_from_stream<S>(
+        stream: S,
+        state: ReaderState,
+    ) -> Result<MultipartReader<'a, E>, MultipartError>
+    where
+        S: Stream<Item = Result<Bytes, E>> + 'a,
+    {
+        if state.version != state::STATE_VERSION {
+            return Err(MultipartError::InvalidReaderState);
+        }
+        let multipart_type = state
+            .multipart_type
+            .parse()
+            .map_err(|_| MultipartError::InvalidReaderState)?;
+
+        let mut reader = MultipartReader::from_stream_with_boundary_and_type(
+            stream,
+            &state.boundary,
+            multipart_type,
+        )?;
+        reader.state = state.phase;
+        reader.finished_cleanly = state.finished_cleanly;
+        reader.depth = state.depth;
+        reader.buf.extend(&state.buffered);
+        reader.pending_item = state.pending.map(|part| MultipartItem {
+            headers: part.headers,
+            data: BytesMut::from(&part.data[..]),
+        });
+        reader.body = BodyState {
+            len: state.body_len,
+            held_crlf: state.held_crlf,
+            discard: state.discard,
+            mid_line: state.mid_line,
+        };
+        Ok(reader)
+    }
+
+    /// Read a document which must consist of exactly one part.
+    ///
+    /// Fails with `MultipartError::MissingPart` or `MultipartError::TooManyParts` otherwise,
+    /// and with `MultipartError::IncompleteDocument` if the terminating boundary is missing.
+    pub async fn into_single_part(mut self) -> Result<MultipartItem, MultipartError> {
+        let item = self.next().await.ok_or(MultipartError::MissingPart)??;
+
+        match self.next().await {
+            None if self.finished_cleanly => Ok(item),
+            None => Err(MultipartError::IncompleteDocument),
+            Some(Ok(_)) => Err(MultipartError::TooManyParts),
+            Some(Err(e)) => Err(e),
+        }
+    }
+
+    /// Consume and discard the rest of the document, e.g. to keep a connection reusable after
+    /// rejecting a request early.
+    ///
+    /// Bodies are dropped as they are parsed instead of being buffered, and parts are not
+    /// checked against the content type allow-list or the deduplicator. The quota and
+    /// preamble limits still apply. A document cut short is not an error, check
+    /// `finished_cleanly` afterwards if needed.
+    ///
+    /// This also works after a part was rejected by a per-part policy, e.g. the allow-list or
+    /// the text policy, in which case the rest of the document is drained after that part.
+    pub async fn drain(&mut self) -> Result<(), MultipartError> {
+        if let Some(state) = self.resume.take() {
+            self.state = state;
+        }
+        self.draining = true;
+        self.body.discard = true;
+        if let Some(item) = &mut self.pending_item {
+            item.data.clear();
+        }
+
+        while let Some(item) = self.next().await {
+            item?;
+        }
+        Ok(())
+    }
+
+    /// Read the next part, writing its body directly into `buf` instead of an allocated item.
+    ///
+    /// The body is written as received, i.e. decompression and text policies are not applied.
+    /// If `buf` runs out of space, `MultipartError::BufferTooSmall` is returned and the
+    /// reader stops. Dropping the future before it completes leaves the part partially read.
+    pub async fn read_part_into<B: BufMut>(
+        &mut self,
+        buf: &mut B,
+    ) -> Option<Result<ExtractedPart, MultipartError>> {
+        let item = futures_util::future::poll_fn(|cx| self.poll_part(cx, Some(&mut *buf))).await;
+
+        Some(item?.map(|item| ExtractedPart {
+            headers: item.headers,
+            len: self.body.len as usize,
+        }))
+    }
+
+    /// Like `read_part_into`, for a fixed-size buffer whose start receives the body
+    pub async fn read_part_into_slice(
+        &mut self,
+        buf: &mut [u8],
+    ) -> Option<Result<ExtractedPart, MultipartError>> {
+        let mut remaining = buf;
+        self.read_part_into(&mut remaining).await
+    }
+
+    /// Record a summary (name, type, size, encoding) of every part for `describe`.
+    ///
+    /// Disabled by default, as endless streams would otherwise grow without bound.
+    pub fn with_part_summaries(mut self) -> Self {
+        self.options.record_summaries = true;
+        self
+    }
+
+    /// Summaries of the parts read so far, see `with_part_summaries`
+    pub fn part_summaries(&self) -> &[PartSummary] {
+        &self.summaries
+    }
+
+    /// A tree-style summary of the parts read so far, without their bodies
+    pub fn describe(&self) -> String {
+        describe::describe(
+            &writer::content_type(&self.multipart_type, &self.boundary),
+            &self.summaries,
+        )
+    }
+
+    /// Returns whether the terminating boundary was seen.
+    ///
+    /// When the source stream ends early the reader simply stops yielding items, so this is
+    /// the way to tell a complete document apart from one cut short by a connection drop.
+    pub fn finished_cleanly(&self) -> bool {
+        self.finished_cleanly
+    }
+
+    /// Pass headers and bodies on unchanged, used when relaying documents
+    pub(crate) fn disable_transformations(&mut self) {
+        self.options.decompression = None;
+        self.options.text_policy = TextPolicy::Bytes;
+        self.options.codecs = None;
+    }
+
+    fn is_duplicate(&self, item: &MultipartItem) -> bool {
+        match (&self.options.deduplicator, item.idempotency_key()) {
+            (Some(deduplicator), Some(key)) => deduplicator.contains(key),
+            _ => false,
+        }
+    }
+
+    /// Record the key of a completely received part
+    fn record_key(&self, item: &MultipartItem) {
+        if let (Some(deduplicator), Some(key)) =
+            (&self.options.deduplicator, item.idempotency_key())
+        {
+            deduplicator.check(key);
+        }
+    }
+
+    /// Post-process a complete part, whose body was written to a sink instead if `extracted`
+    fn finish_item(
+        &mut self,
+        mut item: MultipartItem,
+        extracted: bool,
+    ) -> Result<MultipartItem, MultipartError> {
+        // Record the headers as received, before they are changed by decoding
+        let summary = self
+            .options
+            .record_summaries
+            .then(|| PartSummary::from_headers(&item.headers, None));
+
+        // Bodies in sinks are passed on as received
+        if !extracted {
+            if let Some(codecs) = &self.options.codecs {
+                codecs.decode_item(&mut item)?;
+            }
+
+            if let Some(limits) = &self.options.decompression {
+                compression::decode_item(&mut item, limits)?;
+            }
+
+            if self.multipart_type == MultipartType::FormData {
+                text::apply_policy(&mut item, self.options.text_policy)?;
+            }
+        }
+
+        // Sizes match the headers, i.e. the body as received before decoding
+        if let Some(mut summary) = summary {
+            summary.size = Some(self.body.len);
+            self.summaries.push(summary);
+        }
+
+        Ok(item)
+    }
+
+    /// Move body data in front of the next possible delimiter from the buffer to the body.
+    ///
+    /// Body lines are only inspected one by one where a delimiter may start, so the time spent
+    /// on a body is linear in its size no matter how many lines or near-delimiters it contains.
+    /// Lenient compat profiles recognize more delimiters and always parse line by line.
+    fn skip_body<'s>(
+        &mut self,
+        sink: Option<&mut (dyn BufMut + 's)>,
+    ) -> Result<(), MultipartError> {
+        if self.options.compat != CompatProfile::STRICT || self.pending_item.is_none() {
+            return Ok(());
+        }
+
+        // A delimiter may start at the beginning of the buffer if it is a line start
+        let dash_boundary = &self.delimiter.needle()[2..];
+        let len = self.buf.len().min(dash_boundary.len());
+        if !self.body.mid_line && self.buf[..len] == dash_boundary[..len] {
+            return Ok(());
+        }
+
+        match self.delimiter.find(&self.buf) {
+            Some(idx) => {
+                // The line break in front of the delimiter is held back as usual
+                self.write_body(idx, true, sink)?;
+                self.buf.advance(idx + 2);
+                self.body.mid_line = false;
+            }
+            None => {
+                // Keep what may be the start of a delimiter completed by the next chunk
+                let safe = self.buf.len() - partial_delimiter(self.delimiter.needle(), &self.buf);
+                if safe > 0 {
+                    self.write_body(safe, false, sink)?;
+                    self.buf.advance(safe);
+                    self.body.mid_line = true;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Append the first `len` buffered bytes to the body of the pending item or to `sink`,
+    /// holding back a line break if they are followed by one
+    fn write_body<'s>(
+        &mut self,
+        len: usize,
+        ends_line: bool,
+        sink: Option<&mut (dyn BufMut + 's)>,
+    ) -> Result<(), MultipartError> {
+        put_body(
+            &mut self.body,
+            &mut self.passthrough,
+            &mut self.pending_item,
+            sink,
+            &self.buf[..len],
+            ends_line,
+        )
+    }
+
+    /// Read more data from the source, returns the number of bytes read or `None` at its end.
+    ///
+    /// Body data of buffered sources is emitted straight out of their buffer, only lines which
+    /// may be headers or delimiters and what may be the start of a delimiter completed by the
+    /// next read are copied into the line buffer.
+    fn poll_fill<'s>(
+        &mut self,
+        cx: &mut Context<'_>,
+        sink: Option<&mut (dyn BufMut + 's)>,
+    ) -> Poll<Option<Result<usize, MultipartError>>> {
+        let reader = match &mut self.source {
+            Source::Stream(stream) => {
+                return match stream.as_mut().poll_next(cx) {
+                    Poll::Ready(Some(Ok(data))) => {
+                        self.buf.extend(&data);
+                        Poll::Ready(Some(Ok(data.len())))
+                    }
+                    Poll::Ready(Some(Err(_e))) => {
+                        Poll::Ready(Some(Err(MultipartError::PollingDataFailed)))
+                    }
+                    Poll::Ready(None) => Poll::Ready(None),
+                    Poll::Pending => Poll::Pending,
+                }
+            }
+            Source::BufRead(reader) => reader,
+        };
+
+        let data = match reader.as_mut().poll_fill_buf(cx) {
+            Poll::Ready(Ok([])) => return Poll::Ready(None),
+            Poll::Ready(Ok(data)) => data,
+            Poll::Ready(Err(_e)) => {
+                return Poll::Ready(Some(Err(MultipartError::PollingDataFailed)))
+            }
+            Poll::Pending => return Poll::Pending,
+        };
+
+        // Like `skip_body`, on data which continues the body at a position that can't be the
+        // start of a delimiter without a preceding line break
+        let needle = self.delimiter.needle();
+        let dash_boundary = &needle[2..];
+        let direct = self.state == InnerState::Boundary
+            && self.options.compat == CompatProfile::STRICT
+            && self.pending_item.is_some()
+            && self.buf.is_empty()
+            && (self.body.mid_line || {
+                let len = data.len().min(dash_boundary.len());
+                data[..len] != dash_boundary[..len]
+            });
+
+        let consumed = if !direct {
+            // Lines which may be headers or delimiters are parsed from the line buffer
+            let len = memchr::memchr(b'\n', data).map_or(data.len(), |idx| idx + 1);
+            self.buf.extend(&data[..len]);
+            len
+        } else if let Some(idx) = self.delimiter.find(data) {
+            // The line break in front of the delimiter is held back as usual
+            let body = &data[..idx];
+            put_body(
+                &mut self.body,
+                &mut self.passthrough,
+                &mut self.pending_item,
+                sink,
+                body,
+                true,
+            )?;
+            self.body.mid_line = false;
+            idx + 2
+        } else {
+            let safe = data.len() - partial_delimiter(needle, data);
+            if safe > 0 {
+                let body = &data[..safe];
+                put_body(
+                    &mut self.body,
+                    &mut self.passthrough,
+                    &mut self.pending_item,
+                    sink,
+                    body,
+                    false,
+                )?;
+                self.body.mid_line = true;
+            }
+            self.buf.extend(&data[safe..]);
+            data.len()
+        };
+
+        reader.as_mut().consume(consumed);
+        Poll::Ready(Some(Ok(consumed)))
+    }
+
+    /// Parse the next part. Its body is written to `sink` if given, and into the item otherwise
+    fn poll_part(
+        &mut self,
+        cx: &mut Context<'_>,
+        sink: Option<&mut dyn BufMut>,
+    ) -> Poll<Option<Result<MultipartItem, MultipartError>>> {
+        let result = self.poll_parse(cx, sink);
 
+        // Parts passed through at the end of the document must still reach the sink
+        if let (Poll::Ready(None), Some(passthrough)) = (&result, &mut self.passthrough) {
+            if self.finished_cleanly {
+                passthrough.finish_document();
+            }
+            match passthrough.poll_write_out(cx) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(e)) => {
+                    self.passthrough = None;
+                    return Poll::Ready(Some(Err(e)));
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        result
+    }
+
+    fn poll_parse(
+        &mut self,
+        cx: &mut Context<'_>,
+        mut sink: Option<&mut dyn BufMut>,
+    ) -> Poll<Option<Result<MultipartItem, MultipartError>>> {
         loop {
-            while let Some(idx) = finder.find(&this.buf) {
-                match this.state {
+            if let Some(passthrough) = &mut self.passthrough {
+                match passthrough.poll_write_out(cx) {
+                    Poll::Ready(Ok(())) => {}
+                    Poll::Ready(Err(e)) => {
+                        self.state = InnerState::Eof;
+                        self.passthrough = None;
+                        return Poll::Ready(Some(Err(e)));
+                    }
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+
+            if let Some(quota) = &mut self.quota {
+                match quota.poll_decision(cx) {
+                    Poll::Ready(Ok(())) => {}
+                    Poll::Ready(Err(e)) => {
+                        self.state = InnerState::Eof;
+                        self.quota = None;
+                        return Poll::Ready(Some(Err(e)));
+                    }
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+
+            loop {
+                if self.state == InnerState::Boundary {
+                    if let Err(e) = self.skip_body(sink.as_deref_mut()) {
+                        self.state = InnerState::Eof;
+                        return Poll::Ready(Some(Err(e)));
+                    }
+                }
+                let Some(idx) = self.buf.find_line() else {
+                    break;
+                };
+
+                match self.state {
                     InnerState::FirstBoundary => {
                         // Check if the last line was a boundary
-                        if this.is_boundary(&this.buf[..idx]) {
-                            this.state = InnerState::Headers;
-                        };
+                        let boundary = self.is_boundary(&self.buf[..idx]);
+                        if let Some(preamble) = &mut self.preamble {
+                            let checked = if boundary {
+                                preamble.check(idx + 2)
+                            } else {
+                                preamble.skip(idx + 2)
+                            };
+                            if let Err(e) = checked {
+                                self.state = InnerState::Eof;
+                                return Poll::Ready(Some(Err(e)));
+                            }
+                        }
+                        if boundary {
+                            self.state = InnerState::Headers;
+                        }
                     }
                     InnerState::Boundary => {
                         // Check if the last line was a boundary
-                        if this.is_boundary(&this.buf[..idx]) {
-                            let final_boundary = this.is_final_boundary(&this.buf[..idx]);
+                        if !self.body.mid_line && self.is_boundary(&self.buf[..idx]) {
+                            let final_boundary = self.is_final_boundary(&self.buf[..idx]);
 
                             // If we have a pending item, return it
-                            if let Some(mut item) = this.pending_item.take() {
-                                // Remove last 2 bytes from the data (which were a newline sequence)
-                                item.data.truncate(item.data.len() - 2);
+                            if let Some(item) = self.pending_item.take() {
                                 // Skip to the next line
-                                this.buf.advance(2 + idx);
+                                self.buf.advance(2 + idx);
                                 if final_boundary {
-                                    this.state = InnerState::Eof;
+                                    self.state = InnerState::Eof;
+                                    self.finished_cleanly = true;
                                 } else {
-                                    this.state = InnerState::Headers;
+                                    self.state = InnerState::Headers;
+                                }
+                                if self.body.discard {
+                                    continue;
                                 }
-                                return std::task::Poll::Ready(Some(Ok(item)));
+                                self.record_key(&item);
+                                if let Some(quota) = &mut self.quota {
+                                    quota.record_part();
+                                }
+                                if let Some(passthrough) = &mut self.passthrough {
+                                    if passthrough.is_active() {
+                                        passthrough.finish_part();
+                                        continue;
+                                    }
+                                }
+                                let item = self.finish_item(item, sink.is_some());
+                                if item.is_err() {
+                                    self.resume = Some(self.state);
+                                    self.state = InnerState::Eof;
+                                }
+                                return std::task::Poll::Ready(Some(item));
                             }
 
-                            this.state = InnerState::Headers;
-                            this.pending_item = Some(MultipartItem {
+                            self.state = InnerState::Headers;
+                            self.pending_item = Some(MultipartItem {
                                 headers: vec![],
                                 data: BytesMut::new(),
                             });
                         };
 
-                        // Add the data to the pending item
-                        this.pending_item
-                            .as_mut()
-                            .unwrap()
-                            .data
-                            .extend(&this.buf[..idx + 2])
+                        // Add the line to the body
+                        if let Err(e) = self.write_body(idx, true, sink.as_deref_mut()) {
+                            self.state = InnerState::Eof;
+                            return Poll::Ready(Some(Err(e)));
+                        }
+                        self.body.mid_line = false;
                     }
                     InnerState::Headers => {
                         // Check if we have a pending item or we should create one
-                        if this.pending_item.is_none() {
-                            this.pending_item = Some(MultipartItem {
+                        if self.pending_item.is_none() {
+                            self.pending_item = Some(MultipartItem {
                                 headers: vec![],
                                 data: BytesMut::new(),
                             });
                         }
 
                         // Read the header line and split it into key and value
-                        let header = match str::from_utf8(&this.buf[..idx]) {
+                        let header = match str::from_utf8(&self.buf[..idx]) {
                             Ok(h) => h,
                             Err(_) => {
-                                this.state = InnerState::Eof;
+                                self.state = InnerState::Eof;
                                 return std::task::Poll::Ready(Some(Err(
                                     MultipartError::InvalidItemHeader,
                                 )));
@@ -214,25 +993,74 @@ impl<'a, E> Stream for MultipartReader<'a, E> {
 
                         // This is no header anymore, we are at the end of the headers
                         if header.trim().is_empty() {
-                            this.buf.advance(2 + idx);
-                            this.state = InnerState::Boundary;
+                            self.state = InnerState::Boundary;
+
+                            let item = self.pending_item.as_ref().unwrap();
+                            let allowed = match &self.options.content_types {
+                                Some(filter)
+                                    if !self.draining
+                                        && !filter.allows(
+                                            item.header("content-type"),
+                                            self.multipart_type == MultipartType::FormData
+                                                && item.filename().is_none(),
+                                        ) =>
+                                {
+                                    if filter.policy == ContentTypePolicy::Reject {
+                                        // `drain` continues with the body of the part
+                                        self.body = BodyState {
+                                            discard: true,
+                                            ..BodyState::default()
+                                        };
+                                        if let Some(passthrough) = &mut self.passthrough {
+                                            passthrough.skip_part();
+                                        }
+                                        self.buf.advance(2 + idx);
+                                        self.resume = Some(InnerState::Boundary);
+                                        self.state = InnerState::Eof;
+                                        return Poll::Ready(Some(Err(
+                                            MultipartError::ContentTypeNotAllowed,
+                                        )));
+                                    }
+                                    false
+                                }
+                                _ => true,
+                            };
+                            self.body = BodyState {
+                                discard: self.draining || !allowed || self.is_duplicate(item),
+                                ..BodyState::default()
+                            };
+                            if let Some(passthrough) = &mut self.passthrough {
+                                if self.body.discard {
+                                    passthrough.skip_part();
+                                } else {
+                                    passthrough.start_part(item, &self.buf[..idx + 2]);
+                                }
+                            }
+                            self.buf.advance(2 + idx);
                             continue;
                         }
 
-                        let header_parts: Vec<&str> = header.split(": ").collect();
-                        if header_parts.len() != 2 {
-                            this.state = InnerState::Eof;
+                        if let Some(passthrough) = &mut self.passthrough {
+                            passthrough.push_head(&self.buf[..idx + 2]);
+                        }
+
+                        // Values like filenames may contain colons on their own
+                        let Some((key, value)) =
+                            header.split_once(':').filter(|(key, _)| !key.is_empty())
+                        else {
+                            self.state = InnerState::Eof;
                             return std::task::Poll::Ready(Some(Err(
                                 MultipartError::InvalidItemHeader,
                             )));
-                        }
+                        };
+                        let value = value.trim_start_matches([' ', '\t']);
 
                         // Add header entry to the pending item
-                        this.pending_item
+                        self.pending_item
                             .as_mut()
                             .unwrap()
                             .headers
-                            .push((header_parts[0].to_string(), header_parts[1].to_string()));
+                            .push((key.to_string(), value.to_string()));
                     }
                     InnerState::Eof => {
                         return std::task::Poll::Ready(None);
@@ -240,21 +1068,38 @@ impl<'a, E> Stream for MultipartReader<'a, E> {
                 }
 
                 // Skip to the next line
-                this.buf.advance(2 + idx);
+                self.buf.advance(2 + idx);
+            }
+
+            if let (InnerState::FirstBoundary, Some(preamble)) = (&self.state, &mut self.preamble) {
+                if let Err(e) = preamble.check(self.buf.len()) {
+                    self.state = InnerState::Eof;
+                    return Poll::Ready(Some(Err(e)));
+                }
             }
 
-            // Read more data from the stream
-            match Pin::new(&mut this.stream).poll_next(cx) {
-                Poll::Ready(Some(Ok(data))) => {
-                    this.buf.extend_from_slice(&data);
+            // Read more data from the source
+            match self.poll_fill(cx, sink.as_deref_mut()) {
+                Poll::Ready(Some(Ok(len))) => {
+                    if let Some(quota) = &mut self.quota {
+                        quota.record_bytes(len);
+                    }
                 }
                 Poll::Ready(None) => {
-                    this.state = InnerState::Eof;
+                    // The closing delimiter of a nested document isn't followed by a line break
+                    if self.state == InnerState::Boundary
+                        && !self.body.mid_line
+                        && self.is_final_boundary(&self.buf)
+                    {
+                        self.buf.extend(b"\r\n");
+                        continue;
+                    }
+                    self.state = InnerState::Eof;
                     return std::task::Poll::Ready(None);
                 }
-                Poll::Ready(Some(Err(_e))) => {
-                    this.state = InnerState::Eof;
-                    return std::task::Poll::Ready(Some(Err(MultipartError::PollingDataFailed)));
+                Poll::Ready(Some(Err(e))) => {
+                    self.state = InnerState::Eof;
+                    return std::task::Poll::Ready(Some(Err(e)));
                 }
                 Poll::Pending => {
                     return std::task::Poll::Pending;
@@ -262,6 +1107,101 @@ impl<'a, E> Stream for MultipartReader<'a, E> {
             };
         }
     }
+
+    fn is_final_boundary(&self, data: &[u8]) -> bool {
+        self.boundary_suffix(data)
+            .is_some_and(|suffix| suffix.starts_with(b"--"))
+    }
+
+    fn is_boundary(&self, data: &[u8]) -> bool {
+        self.boundary_suffix(data).is_some()
+    }
+
+    /// Returns what follows the boundary if the line is a delimiter, which per RFC 2046 is
+    /// either nothing or `--` for the final one, followed by optional whitespace
+    fn boundary_suffix<'d>(&self, data: &'d [u8]) -> Option<&'d [u8]> {
+        let suffix = self.options.compat.strip_delimiter(data, &self.boundary)?;
+
+        let rest = suffix.strip_prefix(b"--").unwrap_or(suffix);
+        if rest.iter().all(|c| *c == b' ' || *c == b'\t') {
+            Some(suffix)
+        } else {
+            None
+        }
+    }
+}
+
+/// Append `data` to the body of the pending item or to `sink`, holding back a line break if
+/// it is followed by one
+fn put_body<'s>(
+    body: &mut BodyState,
+    passthrough: &mut Option<Passthrough<'_>>,
+    pending_item: &mut Option<MultipartItem>,
+    sink: Option<&mut (dyn BufMut + 's)>,
+    data: &[u8],
+    ends_line: bool,
+) -> Result<(), MultipartError> {
+    if body.discard {
+        return Ok(());
+    }
+
+    let crlf: &[u8] = if body.held_crlf { b"\r\n" } else { b"" };
+    match (passthrough, sink) {
+        (Some(passthrough), _) if passthrough.is_active() => {
+            passthrough.push_body(crlf);
+            passthrough.push_body(data);
+        }
+        (_, Some(sink)) => {
+            if sink.remaining_mut() < crlf.len() + data.len() {
+                return Err(MultipartError::BufferTooSmall);
+            }
+            sink.put_slice(crlf);
+            sink.put_slice(data);
+        }
+        (_, None) => {
+            let item = &mut pending_item.as_mut().unwrap().data;
+            item.extend_from_slice(crlf);
+            item.extend_from_slice(data);
+        }
+    }
+
+    body.len += (crlf.len() + data.len()) as u64;
+    body.held_crlf = ends_line;
+    Ok(())
+}
+
+/// Length of the longest end of `data` which is the start of the delimiter `needle`
+fn partial_delimiter(needle: &[u8], data: &[u8]) -> usize {
+    (1..needle.len().min(data.len() + 1))
+        .rev()
+        .find(|&len| data.ends_with(&needle[..len]))
+        .unwrap_or(0)
+}
+
+impl<'a> MultipartReader<'a, std::io::Error> {
+    /// Read from a source which buffers on its own, e.g. a buffered file.
+    ///
+    /// Body data is emitted straight out of the buffer of `reader`, only header lines,
+    /// delimiters and incomplete lines at the end of the buffer are copied.
+    pub fn from_async_buf_read<R: AsyncBufRead + 'a>(
+        reader: R,
+        boundary: &str,
+        multipart_type: MultipartType,
+    ) -> Result<MultipartReader<'a, std::io::Error>, MultipartError> {
+        Ok(MultipartReader::new(
+            Source::BufRead(Box::pin(reader)),
+            boundary,
+            multipart_type,
+        ))
+    }
+}
+
+impl<'a, E> Stream for MultipartReader<'a, E> {
+    type Item = Result<MultipartItem, MultipartError>;
+
+    fn poll_next(self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_part(cx, None)
+    }
 }
 
 #[cfg(test)]
@@ -319,5 +1259,340 @@ Content-Type: text/html\r
         }
 
         assert_eq!(items.len(), 3);
+        assert!(reader.finished_cleanly());
+
+        assert_eq!(items[0].name(), Some("text"));
+        assert!(items[0].is_text_field());
+        assert_eq!(items[1].name(), Some("file1"));
+        assert_eq!(items[1].filename(), Some("a.txt"));
+        assert!(!items[1].is_text_field());
+    }
+
+    #[futures_test::test]
+    async fn truncated_request() {
+        let data = b"--boundary\r
+Content-Disposition: form-data; name=\"text\"\r
+\r
+text default\r
+--boundary\r
+Content-Disposition: form-data; name=\"file1\"; filename=\"a.txt\"\r
+\r
+Content of";
+
+        let mut reader = MultipartReader::<std::io::Error>::from_data_with_boundary_and_type(
+            data,
+            "boundary",
+            MultipartType::FormData,
+        )
+        .unwrap();
+
+        let mut items = vec![];
+        while let Some(item) = reader.next().await {
+            items.push(item.unwrap());
+        }
+
+        assert_eq!(items.len(), 1);
+        assert!(!reader.finished_cleanly());
+    }
+
+    #[futures_test::test]
+    async fn read_into_buffers() {
+        let data = b"--boundary\r
+Content-Disposition: form-data; name=\"text\"\r
+\r
+text default\r
+--boundary\r
+Content-Disposition: form-data; name=\"file1\"; filename=\"a.txt\"\r
+\r
+Content of a.txt.\r
+\r
+--boundary\r
+\r
+too large for the slice\r
+--boundary--\r\n";
+        let mut reader = MultipartReader::<std::io::Error>::from_data_with_boundary_and_type(
+            data,
+            "boundary",
+            MultipartType::FormData,
+        )
+        .unwrap();
+
+        let mut buf = vec![];
+        let part = reader.read_part_into(&mut buf).await.unwrap().unwrap();
+        assert_eq!(part.headers.len(), 1);
+        assert_eq!(part.len, 12);
+        assert_eq!(buf, b"text default");
+
+        let mut slice = [0u8; 20];
+        let part = reader
+            .read_part_into_slice(&mut slice)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(&slice[..part.len], b"Content of a.txt.\r\n");
+
+        assert!(matches!(
+            reader.read_part_into_slice(&mut slice).await,
+            Some(Err(MultipartError::BufferTooSmall))
+        ));
+        assert!(reader.read_part_into(&mut buf).await.is_none());
+    }
+
+    #[futures_test::test]
+    async fn async_buf_read_source() {
+        let data = &b"--boundary\r
+Content-Disposition: form-data; name=\"text\"\r
+\r
+text default\r
+--boundary\r
+\r
+second\r
+--boundary--\r\n"[..];
+        let source = futures_util::io::BufReader::with_capacity(7, data);
+        let mut reader =
+            MultipartReader::from_async_buf_read(source, "boundary", MultipartType::FormData)
+                .unwrap();
+
+        let mut bodies = vec![];
+        while let Some(item) = reader.next().await {
+            bodies.push(item.unwrap().data.to_vec());
+        }
+        assert_eq!(bodies, vec![b"text default".to_vec(), b"second".to_vec()]);
+        assert!(reader.finished_cleanly());
+
+        // Bodies emitted from the buffer of the source are split at any position
+        let body = b"x--boundary\r\nX\r\r\n--boundarX\r\n\r\n--boundary-\r\nrest\r";
+        let mut data = b"--boundary\r\n\r\n".to_vec();
+        data.extend_from_slice(body);
+        data.extend_from_slice(b"\r\n--boundary\r\n\r\n\r\n--boundary--\r\n");
+        for capacity in 1..=data.len() {
+            let source = futures_util::io::BufReader::with_capacity(capacity, &data[..]);
+            let reader =
+                MultipartReader::from_async_buf_read(source, "boundary", MultipartType::Mixed)
+                    .unwrap();
+
+            let items: Vec<_> = reader.map(|item| item.unwrap().data).collect().await;
+            assert_eq!(items.len(), 2, "capacity {}", capacity);
+            assert_eq!(&items[0][..], &body[..], "capacity {}", capacity);
+            assert_eq!(&items[1][..], b"", "capacity {}", capacity);
+        }
+    }
+
+    #[futures_test::test]
+    async fn tiny_and_empty_chunks() {
+        let body = vec![b'x'; 256 * 1024];
+        let mut data = b"--boundary\r\n\r\nfirst\r\r\n--boundary\r\n\r\n".to_vec();
+        data.extend_from_slice(&body);
+        data.extend_from_slice(b"\r\n--boundary--\r\n");
+
+        // Every byte is delivered on its own, surrounded by empty chunks
+        let chunks = data.iter().flat_map(|byte| {
+            [
+                Ok::<_, std::io::Error>(Bytes::new()),
+                Ok(Bytes::copy_from_slice(&[*byte])),
+                Ok(Bytes::new()),
+            ]
+        });
+        let mut reader = MultipartReader::from_stream_with_boundary_and_type(
+            futures_util::stream::iter(chunks),
+            "boundary",
+            MultipartType::Mixed,
+        )
+        .unwrap();
+
+        let mut items = vec![];
+        while let Some(item) = reader.next().await {
+            items.push(item.unwrap());
+        }
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(&items[0].data[..], b"first\r");
+        assert_eq!(&items[1].data[..], &body[..]);
+        assert!(reader.finished_cleanly());
+    }
+
+    #[futures_test::test]
+    async fn nested_documents() {
+        let data = b"--outer\r
+Content-Type: multipart/mixed; boundary=inner\r
+\r
+--inner\r
+Content-Type: multipart/mixed; boundary=innermost\r
+\r
+--innermost\r
+\r
+deep\r
+--innermost--\r
+--inner--\r
+--outer--\r\n";
+
+        let read = |max_depth| async move {
+            let mut outer = MultipartReader::<std::io::Error>::from_data_with_boundary_and_type(
+                data,
+                "outer",
+                MultipartType::FormData,
+            )
+            .unwrap()
+            .with_max_depth(max_depth);
+
+            let item = outer.next().await.unwrap()?;
+            let mut inner = outer.nested_reader(&item)?;
+            let item = inner.next().await.unwrap()?;
+            let mut innermost = inner.nested_reader(&item)?;
+            innermost.next().await.unwrap()
+        };
+
+        assert_eq!(&read(2).await.unwrap().data[..], b"deep");
+        assert!(matches!(read(1).await, Err(MultipartError::NestingTooDeep)));
+    }
+
+    #[futures_test::test]
+    async fn single_part() {
+        let reader = |data: &[u8]| {
+            MultipartReader::<std::io::Error>::from_data_with_boundary_and_type(
+                data,
+                "boundary",
+                MultipartType::Mixed,
+            )
+            .unwrap()
+        };
+
+        let item = reader(b"--boundary\r\nX-Event: push\r\n\r\n{}\r\n--boundary--\r\n")
+            .into_single_part()
+            .await
+            .unwrap();
+        assert_eq!(item.header("x-event"), Some("push"));
+        assert_eq!(&item.data[..], b"{}");
+
+        assert!(matches!(
+            reader(b"preamble only").into_single_part().await,
+            Err(MultipartError::MissingPart)
+        ));
+        assert!(matches!(
+            reader(b"--boundary\r\n\r\na\r\n--boundary\r\n\r\nb\r\n--boundary--\r\n")
+                .into_single_part()
+                .await,
+            Err(MultipartError::TooManyParts)
+        ));
+        assert!(matches!(
+            reader(b"--boundary\r\n\r\n{}\r\n--boundary\r\n\r\npartial")
+                .into_single_part()
+                .await,
+            Err(MultipartError::IncompleteDocument)
+        ));
+    }
+
+    #[futures_test::test]
+    async fn drain_remainder() {
+        let data = b"--boundary\r
+Content-Type: text/plain\r
+\r
+first\r
+--boundary\r
+Content-Type: image/png\r
+\r
+second\r
+--boundary\r
+\r
+third\r
+--boundary--\r\n";
+        let mut reader = MultipartReader::<std::io::Error>::from_data_with_boundary_and_type(
+            data,
+            "boundary",
+            MultipartType::Mixed,
+        )
+        .unwrap()
+        .with_allowed_content_types(&["text/plain"], ContentTypePolicy::Reject);
+
+        let item = reader.next().await.unwrap().unwrap();
+        assert_eq!(&item.data[..], b"first");
+
+        reader.drain().await.unwrap();
+        assert!(reader.finished_cleanly());
+        assert!(reader.next().await.is_none());
+
+        // Draining after a rejected part consumes the rest of the source
+        let polled = std::rc::Rc::new(std::cell::Cell::new(0));
+        let chunks = [
+            &b"--boundary\r\nContent-Type: image/png\r\n\r\n"[..],
+            b"png\r\n",
+            b"--boundary\r\n\r\nnext\r\n",
+            b"--boundary--\r\n",
+        ];
+        let counter = polled.clone();
+        let stream = futures_util::stream::iter(chunks).map(move |chunk| {
+            counter.set(counter.get() + 1);
+            Ok::<_, std::io::Error>(Bytes::from_static(chunk))
+        });
+        let mut reader = MultipartReader::from_stream_with_boundary_and_type(
+            stream,
+            "boundary",
+            MultipartType::Mixed,
+        )
+        .unwrap()
+        .with_allowed_content_types(&["text/plain"], ContentTypePolicy::Reject);
+
+        assert!(matches!(
+            reader.next().await,
+            Some(Err(MultipartError::ContentTypeNotAllowed))
+        ));
+        reader.drain().await.unwrap();
+        assert_eq!(polled.get(), 4);
+        assert!(reader.finished_cleanly());
+    }
+
+    #[futures_test::test]
+    async fn near_delimiters_in_body() {
+        let mut body = b"--boundarX\r\n".to_vec();
+        for _ in 0..1000 {
+            body.extend_from_slice(b"\r\n--boundarX\r\n--boundary-\r\n--bound\r");
+        }
+        let mut data = b"--boundary\r\n\r\n".to_vec();
+        data.extend_from_slice(&body);
+        data.extend_from_slice(b"\r\n--boundary\r\n\r\n\r\n--boundary--\r\n");
+
+        for chunk_size in [1, 7, 8192] {
+            let chunks: Vec<Result<Bytes, std::io::Error>> = data
+                .chunks(chunk_size)
+                .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+                .collect();
+            let reader = MultipartReader::from_stream_with_boundary_and_type(
+                futures_util::stream::iter(chunks),
+                "boundary",
+                MultipartType::Mixed,
+            )
+            .unwrap();
+
+            let items: Vec<_> = reader.map(|item| item.unwrap().data).collect().await;
+            assert_eq!(items.len(), 2);
+            assert_eq!(&items[0][..], &body[..]);
+            assert_eq!(&items[1][..], b"");
+        }
+    }
+
+    #[futures_test::test]
+    async fn same_result_at_every_split() {
+        let body = format!(
+            "{}--boundary\r\n--boundarX\r\n\r\n--boundary-\r\nrest",
+            "X".repeat(30)
+        );
+        let data = format!("--boundary\r\n\r\n{}\r\n--boundary--\r\n", body).into_bytes();
+
+        for split in 0..=data.len() {
+            let chunks: Vec<Result<Bytes, std::io::Error>> = vec![
+                Ok(Bytes::copy_from_slice(&data[..split])),
+                Ok(Bytes::copy_from_slice(&data[split..])),
+            ];
+            let reader = MultipartReader::from_stream_with_boundary_and_type(
+                futures_util::stream::iter(chunks),
+                "boundary",
+                MultipartType::Mixed,
+            )
+            .unwrap();
+
+            let items: Vec<_> = reader.map(|item| item.unwrap().data).collect().await;
+            assert_eq!(items.len(), 1, "split at {}", split);
+            assert_eq!(&items[0][..], body.as_bytes(), "split at {}", split);
+        }
     }
 }
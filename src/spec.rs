@@ -0,0 +1,175 @@
+use std::{collections::BTreeMap, fs::File, path::PathBuf};
+
+use serde::Deserialize;
+
+use crate::{
+    error::MultipartError,
+    multipart_type::MultipartType,
+    part::MultipartPart,
+    writer::{self, MultipartWriter},
+};
+
+/// Declarative description of a document, deserializable from e.g. JSON or YAML:
+///
+/// ```json
+/// {
+///     "type": "form-data",
+///     "parts": [
+///         { "name": "text", "value": "text default" },
+///         { "name": "file", "filename": "a.txt", "path": "a.txt", "encoding": "gzip" }
+///     ]
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct DocumentSpec {
+    /// Multipart subtype, `form-data` by default
+    #[serde(rename = "type", default = "default_type")]
+    pub multipart_type: String,
+
+    /// Boundary to use, random by default
+    #[serde(default)]
+    pub boundary: Option<String>,
+
+    pub parts: Vec<PartSpec>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PartSpec {
+    /// Name put into the Content-Disposition header
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// Filename put into the Content-Disposition header
+    #[serde(default)]
+    pub filename: Option<String>,
+
+    #[serde(default)]
+    pub content_type: Option<String>,
+
+    /// Content encoding applied to the body, see `MultipartPart::with_encoding`
+    #[serde(default)]
+    pub encoding: Option<String>,
+
+    /// Additional headers
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+
+    #[serde(flatten)]
+    pub source: SourceSpec,
+}
+
+/// Where the body of a part comes from
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceSpec {
+    /// Inline value
+    Value(String),
+
+    /// File which is streamed while the document is emitted
+    Path(PathBuf),
+}
+
+fn default_type() -> String {
+    "form-data".to_string()
+}
+
+impl DocumentSpec {
+    /// Create a writer with all parts of the spec appended
+    pub fn build(&self) -> Result<MultipartWriter, MultipartError> {
+        let multipart_type = self.multipart_type.parse::<MultipartType>()?;
+
+        let mut writer = match &self.boundary {
+            Some(boundary) => MultipartWriter::new_with_boundary(boundary, multipart_type)?,
+            None => MultipartWriter::new(multipart_type),
+        };
+
+        for part in &self.parts {
+            let part = part.build(&writer.multipart_type)?;
+            writer.append_part(part)?;
+        }
+        Ok(writer)
+    }
+}
+
+impl PartSpec {
+    fn build(&self, multipart_type: &MultipartType) -> Result<MultipartPart, MultipartError> {
+        let mut part = match &self.source {
+            SourceSpec::Value(value) => MultipartPart::new(value.clone()),
+            SourceSpec::Path(path) => {
+                let file = File::open(path).map_err(|_| MultipartError::ReadingSourceFailed)?;
+                MultipartPart::from_reader(file)
+            }
+        };
+
+        if self.name.is_some() || self.filename.is_some() {
+            let disposition = writer::content_disposition(
+                multipart_type,
+                self.name.as_deref(),
+                self.filename.as_deref(),
+            );
+            part = part.with_header("Content-Disposition", &disposition);
+        }
+
+        if let Some(content_type) = &self.content_type {
+            part = part.with_header("Content-Type", content_type);
+        }
+        for (key, value) in &self.headers {
+            part = part.with_header(key, value);
+        }
+
+        match &self.encoding {
+            Some(encoding) => part.with_encoding(encoding),
+            None => Ok(part),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_writer_from_json() {
+        let path =
+            std::env::temp_dir().join(format!("multipart-rs-spec-{}.txt", std::process::id()));
+        std::fs::write(&path, "Content of a.txt.").unwrap();
+
+        let spec = serde_json::json!({
+            "boundary": "boundary",
+            "parts": [
+                { "name": "text", "value": "text default", "headers": { "X-Test": "1" } },
+                { "name": "file", "filename": "a.txt", "content_type": "text/plain", "path": path }
+            ]
+        });
+        let spec: DocumentSpec = serde_json::from_value(spec).unwrap();
+        let data = spec.build().unwrap().finish().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            data,
+            &b"--boundary\r
+Content-Disposition: form-data; name=\"text\"\r
+X-Test: 1\r
+\r
+text default\r
+--boundary\r
+Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r
+Content-Type: text/plain\r
+\r
+Content of a.txt.\r
+--boundary--\r\n"[..]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_encoding() {
+        let spec: DocumentSpec = serde_json::from_str(
+            r#"{ "type": "mixed", "parts": [{ "value": "data", "encoding": "x-unknown" }] }"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            spec.build(),
+            Err(MultipartError::UnsupportedEncoding)
+        ));
+    }
+}
@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+
+use crate::reader::InnerState;
+
+/// Version of the serialized format, bumped on incompatible changes
+pub(crate) const STATE_VERSION: u32 = 1;
+
+/// Snapshot of a reader mid-document, to hand a connection over to another process.
+///
+/// Holds the position of the parser, the bytes received but not parsed yet and the part
+/// being read. Created by `MultipartReader::save_state` and restored with
+/// `MultipartReader::resume_from_stream`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReaderState {
+    pub(crate) version: u32,
+    pub(crate) boundary: String,
+    pub(crate) multipart_type: String,
+    pub(crate) phase: InnerState,
+    pub(crate) finished_cleanly: bool,
+    pub(crate) depth: usize,
+
+    /// Bytes received from the source which were not parsed yet
+    pub(crate) buffered: Vec<u8>,
+
+    /// The part being read
+    pub(crate) pending: Option<PendingPart>,
+    pub(crate) body_len: u64,
+    pub(crate) held_crlf: bool,
+    pub(crate) discard: bool,
+    #[serde(default)]
+    pub(crate) mid_line: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct PendingPart {
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) data: Vec<u8>,
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use futures_util::{FutureExt, StreamExt};
+
+    use super::*;
+    use crate::{error::MultipartError, multipart_type::MultipartType, reader::MultipartReader};
+
+    #[futures_test::test]
+    async fn resumes_in_another_reader() {
+        let (mut tx, rx) = futures_channel::mpsc::channel::<Result<Bytes, std::io::Error>>(4);
+        tx.try_send(Ok(Bytes::from_static(
+            b"--boundary\r\n\r\nfirst\r\n--boundary\r\nX-Test: 1\r\n\r\nsec",
+        )))
+        .unwrap();
+
+        let mut reader = MultipartReader::from_stream_with_boundary_and_type(
+            rx,
+            "boundary",
+            MultipartType::Mixed,
+        )
+        .unwrap();
+        let first = reader.next().await.unwrap().unwrap();
+        assert_eq!(&first.data[..], b"first");
+        // Wait for more data
+        assert!(reader.next().now_or_never().is_none());
+
+        let json = serde_json::to_string(&reader.save_state()).unwrap();
+        let state: ReaderState = serde_json::from_str(&json).unwrap();
+
+        let rest = futures_util::stream::iter([Ok::<_, std::io::Error>(Bytes::from_static(
+            b"ond\r\n--boundary--\r\n",
+        ))]);
+        let mut resumed = MultipartReader::resume_from_stream(rest, state).unwrap();
+        let second = resumed.next().await.unwrap().unwrap();
+        assert_eq!(second.header("x-test"), Some("1"));
+        assert_eq!(&second.data[..], b"second");
+        assert!(resumed.next().await.is_none());
+        assert!(resumed.finished_cleanly());
+
+        let mut state = reader.save_state();
+        state.version += 1;
+        assert!(matches!(
+            MultipartReader::resume_from_stream(
+                futures_util::stream::empty::<Result<Bytes, std::io::Error>>(),
+                state
+            ),
+            Err(MultipartError::InvalidReaderState)
+        ));
+    }
+}
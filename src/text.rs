@@ -0,0 +1,82 @@
+use std::borrow::Cow;
+
+use bytes::BytesMut;
+
+use crate::{error::MultipartError, reader::MultipartItem};
+
+/// How the reader treats the values of text form fields
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TextPolicy {
+    /// Expose the raw bytes without any validation
+    #[default]
+    Bytes,
+
+    /// Fail with `MultipartError::InvalidUtf8` if a value is not valid UTF-8
+    Strict,
+
+    /// Replace invalid UTF-8 sequences with U+FFFD
+    Lossy,
+}
+
+/// Apply the policy to a form-data item, file uploads and non-text parts are left untouched
+pub(crate) fn apply_policy(
+    item: &mut MultipartItem,
+    policy: TextPolicy,
+) -> Result<(), MultipartError> {
+    if policy == TextPolicy::Bytes || !item.is_text_field() {
+        return Ok(());
+    }
+
+    match String::from_utf8_lossy(&item.data) {
+        Cow::Borrowed(_) => Ok(()),
+        Cow::Owned(_) if policy == TextPolicy::Strict => Err(MultipartError::InvalidUtf8),
+        Cow::Owned(text) => {
+            item.data = BytesMut::from(text.as_bytes());
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use super::*;
+    use crate::{multipart_type::MultipartType, reader::MultipartReader};
+
+    const DATA: &[u8] = b"--boundary\r
+Content-Disposition: form-data; name=\"text\"\r
+\r
+caf\xe9\r
+--boundary\r
+Content-Disposition: form-data; name=\"file\"; filename=\"a.bin\"\r
+\r
+\xff\xfe\r
+--boundary--\r\n";
+
+    fn reader(policy: TextPolicy) -> MultipartReader<'static, std::io::Error> {
+        MultipartReader::from_data_with_boundary_and_type(DATA, "boundary", MultipartType::FormData)
+            .unwrap()
+            .with_text_policy(policy)
+    }
+
+    #[futures_test::test]
+    async fn strict_rejects_invalid_text() {
+        let mut reader = reader(TextPolicy::Strict);
+        assert!(matches!(
+            reader.next().await,
+            Some(Err(MultipartError::InvalidUtf8))
+        ));
+    }
+
+    #[futures_test::test]
+    async fn lossy_replaces_invalid_text() {
+        let mut reader = reader(TextPolicy::Lossy);
+        let text = reader.next().await.unwrap().unwrap();
+        assert_eq!(&text.data[..], "caf\u{fffd}".as_bytes());
+
+        // File uploads keep their raw bytes
+        let file = reader.next().await.unwrap().unwrap();
+        assert_eq!(&file.data[..], b"\xff\xfe");
+    }
+}
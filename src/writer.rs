@@ -1 +1,1198 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{ErrorKind, Read, Seek, SeekFrom},
+    path::Path,
+    sync::Arc,
+};
 
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::{
+    boundary::{BoundaryStrategy, RandomHex},
+    clock::{Clock, SystemClock},
+    codec::CodecRegistry,
+    describe::{self, PartSummary},
+    digest::{BodyDigest, Crc32},
+    error::MultipartError,
+    manifest::{self, BodyRecord},
+    multipart_type::MultipartType,
+    part::MultipartPart,
+};
+
+/// Size of the chunks read from part sources
+const SOURCE_CHUNK_SIZE: usize = 8192;
+
+pub(crate) enum Chunk {
+    /// Encoded data
+    Data(Bytes),
+
+    /// Body which is read lazily while taking chunks
+    Source(Box<dyn Read + Send>),
+}
+
+/// What a queued chunk is to the manifest
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ChunkRole {
+    /// Delimiters, headers and bodies which are not recorded
+    Other,
+
+    /// Recorded body of the part with the index
+    Body(usize),
+
+    /// Placeholder for the trailer part, which is encoded once it is taken
+    Trailer,
+}
+
+/// A header whose value is computed from the body of a part
+pub trait DeferredHeader {
+    /// Feed the next piece of the body
+    fn update(&mut self, data: &[u8]);
+
+    /// Return the header after the whole body was fed
+    fn finish(&self) -> (String, String);
+}
+
+/// Computes the Content-Length header of a part
+#[derive(Debug, Default)]
+pub struct ContentLength {
+    len: u64,
+}
+
+impl DeferredHeader for ContentLength {
+    fn update(&mut self, data: &[u8]) {
+        self.len += data.len() as u64;
+    }
+
+    fn finish(&self) -> (String, String) {
+        ("Content-Length".to_string(), self.len.to_string())
+    }
+}
+
+/// Details of the emitted syntax, to match servers with particular expectations
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutputProfile {
+    /// Emit a line break after the closing delimiter
+    pub final_crlf: bool,
+
+    /// Quote the boundary in the Content-Type even if it is a token
+    pub quote_boundary: bool,
+
+    /// Only accept header names which are RFC 7230 tokens and values of visible characters
+    pub strict_headers: bool,
+}
+
+impl OutputProfile {
+    /// The default output, which most servers accept
+    pub const DEFAULT: OutputProfile = OutputProfile {
+        final_crlf: true,
+        quote_boundary: false,
+        strict_headers: false,
+    };
+
+    /// Like the default, but rejects headers outside of the RFC 7230 grammar
+    pub const STRICT: OutputProfile = OutputProfile {
+        final_crlf: true,
+        quote_boundary: false,
+        strict_headers: true,
+    };
+}
+
+impl Default for OutputProfile {
+    fn default() -> Self {
+        OutputProfile::DEFAULT
+    }
+}
+
+pub struct MultipartWriter {
+    pub boundary: String,
+    pub multipart_type: MultipartType,
+    /// Encoded chunks which were not taken yet
+    chunks: VecDeque<(Chunk, ChunkRole)>,
+    /// Summaries of the appended parts
+    summaries: Vec<PartSummary>,
+    profile: OutputProfile,
+    /// Maximum size of the encoded document, if limited
+    max_size: Option<u64>,
+    /// Encoded size of the appended parts, bodies of sources count once they were read
+    size: u64,
+    /// Custom codecs encoding the bodies of matching parts
+    codecs: Option<CodecRegistry>,
+    /// Sizes and checksums of the emitted bodies by part, `None` for parts appended before
+    /// recording was enabled
+    records: Vec<Option<BodyRecord>>,
+    /// Whether the bodies of appended parts are recorded for the manifest
+    record_bodies: bool,
+    /// Digest of the recorded bodies
+    digest: Box<dyn BodyDigest>,
+    clock: Box<dyn Clock + Send>,
+    /// Name of the trailer part appended when the document is closed, if enabled and not
+    /// encoded yet
+    trailer: Option<String>,
+    /// Upper bound of the length of the parts listed in the trailer
+    trailer_parts_len: u64,
+}
+
+impl MultipartWriter {
+    /// Create a writer with a random hexadecimal boundary
+    pub fn new(multipart_type: MultipartType) -> MultipartWriter {
+        MultipartWriter {
+            boundary: RandomHex::default().generate(),
+            multipart_type,
+            chunks: VecDeque::new(),
+            summaries: vec![],
+            profile: OutputProfile::DEFAULT,
+            max_size: None,
+            size: 0,
+            codecs: None,
+            records: vec![],
+            record_bodies: false,
+            digest: Box::new(Crc32::default()),
+            clock: Box::new(SystemClock),
+            trailer: None,
+            trailer_parts_len: 0,
+        }
+    }
+
+    /// Create a writer with a boundary generated by `strategy`
+    pub fn new_with_strategy<S: BoundaryStrategy + ?Sized>(
+        multipart_type: MultipartType,
+        strategy: &mut S,
+    ) -> Result<MultipartWriter, MultipartError> {
+        MultipartWriter::new_with_boundary(&strategy.generate(), multipart_type)
+    }
+
+    pub fn new_with_boundary(
+        boundary: &str,
+        multipart_type: MultipartType,
+    ) -> Result<MultipartWriter, MultipartError> {
+        validate_boundary(boundary)?;
+
+        Ok(MultipartWriter {
+            boundary: boundary.to_string(),
+            multipart_type,
+            chunks: VecDeque::new(),
+            summaries: vec![],
+            profile: OutputProfile::DEFAULT,
+            max_size: None,
+            size: 0,
+            codecs: None,
+            records: vec![],
+            record_bodies: false,
+            digest: Box::new(Crc32::default()),
+            clock: Box::new(SystemClock),
+            trailer: None,
+            trailer_parts_len: 0,
+        })
+    }
+
+    /// Set the details of the emitted syntax, which apply to parts appended afterwards
+    pub fn with_output_profile(mut self, profile: OutputProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Limit the encoded document, including the closing delimiter, to `max_size` bytes.
+    ///
+    /// Appending a part fails with `MultipartError::DocumentTooLarge` if its size is known
+    /// (in-memory bodies or a Content-Length header) and would exceed the limit. Bodies of
+    /// sources are counted while they are read, so taking chunks fails as soon as one of
+    /// them exceeds the limit.
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// Encode the bodies of parts appended afterwards which match one of the `codecs`.
+    ///
+    /// Codecs work on complete bodies, so bodies of matching sources are read into memory
+    /// when the part is appended.
+    pub fn with_codecs(mut self, codecs: CodecRegistry) -> Self {
+        self.codecs = Some(codecs);
+        self
+    }
+
+    /// Record the size and checksum of the bodies of parts appended afterwards while they are
+    /// emitted, for `finish_with_manifest`.
+    ///
+    /// The default CRC-32 checksum only detects accidental corruption, e.g. a truncated or
+    /// garbled transfer. Use `with_digest` to tell whether a document was tampered with.
+    pub fn with_manifest(mut self) -> Self {
+        self.record_bodies = true;
+        self
+    }
+
+    /// Append a final `application/json` part named `name` in front of the closing delimiter,
+    /// which lets the receiver detect truncated or corrupted transfers:
+    ///
+    /// ```json
+    /// {
+    ///     "count": 1,
+    ///     "size": 122,
+    ///     "checksum_algorithm": "crc32",
+    ///     "parts": [
+    ///         { "name": "file", "filename": "a.txt", "content_type": "text/plain",
+    ///           "encoding": null, "size": 17, "checksum": "318f9c13" }
+    ///     ]
+    /// }
+    /// ```
+    ///
+    /// `count` is the number of parts and `size` the number of bytes in front of the trailer.
+    /// The trailer is encoded once all other parts were emitted, so sizes and checksums are
+    /// recorded as for `with_manifest`. It does not appear in the part summaries. Room for the
+    /// trailer is reserved when parts are appended, assuming the largest possible sizes, so
+    /// `with_max_size` rejects parts which would leave no room for it.
+    pub fn with_trailer(mut self, name: &str) -> Self {
+        self.trailer = Some(name.to_string());
+        self.record_bodies = true;
+        self.update_trailer_parts_len();
+        self
+    }
+
+    /// Checksum the recorded bodies with `digest` instead of CRC-32.
+    ///
+    /// The manifest names a single algorithm, so set the digest before appending parts.
+    pub fn with_digest<D: BodyDigest + 'static>(mut self, digest: D) -> Self {
+        self.digest = Box::new(digest);
+        self.update_trailer_parts_len();
+        self
+    }
+
+    /// Take the current time from `clock` instead of the system time
+    pub fn with_clock<C: Clock + Send + 'static>(mut self, clock: C) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// The Content-Type header value describing the written document
+    pub fn content_type(&self) -> String {
+        if self.profile.quote_boundary {
+            format!(
+                "multipart/{}; boundary=\"{}\"",
+                self.multipart_type, self.boundary
+            )
+        } else {
+            content_type(&self.multipart_type, &self.boundary)
+        }
+    }
+
+    /// Append a part to the document.
+    ///
+    /// The body is not copied, so `Bytes` bodies are shared with the caller.
+    pub fn append<B: Into<Bytes>>(
+        &mut self,
+        headers: &[(String, String)],
+        data: B,
+    ) -> Result<(), MultipartError> {
+        self.push_part(headers, Chunk::Data(data.into()))
+    }
+
+    /// Append a part built with `MultipartPart`
+    pub fn append_part(&mut self, part: MultipartPart) -> Result<(), MultipartError> {
+        self.push_part(&part.headers, part.body)
+    }
+
+    /// Append a part whose body is read from `source` while the document is emitted
+    pub fn append_reader<R: Read + Send + 'static>(
+        &mut self,
+        headers: &[(String, String)],
+        source: R,
+    ) -> Result<(), MultipartError> {
+        self.push_part(headers, Chunk::Source(Box::new(source)))
+    }
+
+    /// Append a file, which is streamed while the document is emitted.
+    ///
+    /// The Content-Type is `content_type` if given, otherwise it is guessed from the extension
+    /// with the `mime_guess` feature and `application/octet-stream` without.
+    pub fn add_file<P: AsRef<Path>>(
+        &mut self,
+        name: &str,
+        path: P,
+        content_type: Option<&str>,
+    ) -> Result<(), MultipartError> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|_| MultipartError::ReadingSourceFailed)?;
+
+        let filename = path.file_name().map(|f| f.to_string_lossy());
+        let content_type = match content_type {
+            Some(content_type) => content_type.to_string(),
+            None => guess_content_type(path),
+        };
+        let headers = [
+            (
+                "Content-Disposition".to_string(),
+                content_disposition(&self.multipart_type, Some(name), filename.as_deref()),
+            ),
+            ("Content-Type".to_string(), content_type),
+        ];
+        self.append_reader(&headers, file)
+    }
+
+    /// Append one text part per key/value pair, named after the key.
+    ///
+    /// Eases moving clients from `application/x-www-form-urlencoded` bodies to forms which
+    /// also carry files. The pairs are expected to be decoded already.
+    pub fn append_urlencoded_fields<I, K, V>(&mut self, pairs: I) -> Result<(), MultipartError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        for (key, value) in pairs {
+            let headers = [(
+                "Content-Disposition".to_string(),
+                content_disposition(&self.multipart_type, Some(key.as_ref()), None),
+            )];
+            self.append(&headers, Bytes::copy_from_slice(value.as_ref().as_bytes()))?;
+        }
+        Ok(())
+    }
+
+    /// Append a part from a seekable source with headers that depend on its body.
+    ///
+    /// The source is read once to compute the `deferred` headers (e.g. `ContentLength` or a
+    /// digest), then rewound and read again while the document is emitted, so the body is
+    /// never held in memory. If the source turns out not to be seekable, the deferred headers
+    /// are omitted and the body is streamed as is. The source must not change in between.
+    pub fn append_seekable<R: Read + Seek + Send + 'static>(
+        &mut self,
+        headers: &[(String, String)],
+        mut source: R,
+        mut deferred: Vec<Box<dyn DeferredHeader>>,
+    ) -> Result<(), MultipartError> {
+        let Ok(start) = source.stream_position() else {
+            return self.append_reader(headers, source);
+        };
+
+        let mut buf = [0u8; SOURCE_CHUNK_SIZE];
+        loop {
+            let len = match source.read(&mut buf) {
+                Ok(0) => break,
+                Ok(len) => len,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => return Err(MultipartError::ReadingSourceFailed),
+            };
+            for header in deferred.iter_mut() {
+                header.update(&buf[..len]);
+            }
+        }
+        source
+            .seek(SeekFrom::Start(start))
+            .map_err(|_| MultipartError::ReadingSourceFailed)?;
+
+        let mut headers = headers.to_vec();
+        headers.extend(deferred.iter().map(|header| header.finish()));
+        self.append_reader(&headers, source)
+    }
+
+    /// Append a part whose body was registered in a `PartCache`, sharing its memory
+    pub fn append_cached(
+        &mut self,
+        headers: &[(String, String)],
+        data: &Arc<Bytes>,
+    ) -> Result<(), MultipartError> {
+        self.append(headers, Bytes::clone(data))
+    }
+
+    /// Summaries of the parts appended so far
+    pub fn part_summaries(&self) -> &[PartSummary] {
+        &self.summaries
+    }
+
+    /// A tree-style summary of the appended parts, without their bodies.
+    ///
+    /// Sizes are those of the encoded bodies. The size of streamed bodies is known once they
+    /// were emitted if recorded for the manifest, and otherwise only if they declare a
+    /// Content-Length.
+    pub fn describe(&self) -> String {
+        describe::describe(&self.content_type(), &self.summaries)
+    }
+
+    /// Take the next encoded chunk of the document, if any.
+    ///
+    /// This allows sending the document while parts are still being appended. Bodies from
+    /// part sources are read here.
+    pub fn take_chunk(&mut self) -> Result<Option<Bytes>, MultipartError> {
+        loop {
+            let (mut source, role) = match self.chunks.pop_front() {
+                None => return Ok(None),
+                Some((_, ChunkRole::Trailer)) => return self.encode_trailer().map(Some),
+                Some((Chunk::Data(data), role)) => {
+                    self.record_body(role, &data);
+                    return Ok(Some(data));
+                }
+                Some((Chunk::Source(source), role)) => (source, role),
+            };
+
+            let mut buf = BytesMut::zeroed(SOURCE_CHUNK_SIZE);
+            match source.read(&mut buf) {
+                // The source is exhausted, continue with the next chunk
+                Ok(0) => self.finish_body(role),
+                Ok(len) => {
+                    self.size += len as u64;
+                    self.check_size(0, None)?;
+                    self.chunks.push_front((Chunk::Source(source), role));
+                    buf.truncate(len);
+                    self.record_body(role, &buf);
+                    return Ok(Some(buf.freeze()));
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {
+                    self.chunks.push_front((Chunk::Source(source), role));
+                }
+                Err(_) => return Err(MultipartError::ReadingSourceFailed),
+            }
+        }
+    }
+
+    /// Close the document and return all chunks which were not taken yet
+    pub fn finish(mut self) -> Result<Bytes, MultipartError> {
+        self.close();
+
+        let mut data = BytesMut::new();
+        while let Some(chunk) = self.take_chunk()? {
+            data.extend_from_slice(&chunk);
+        }
+        Ok(data.freeze())
+    }
+
+    /// Close the document and return all chunks which were not taken yet, along with a JSON
+    /// manifest of the document.
+    ///
+    /// The manifest lists the Content-Type, size and creation time (in seconds since the Unix
+    /// epoch, from the clock of the writer) of the document and the name, filename,
+    /// Content-Type, encoding, size and CRC-32 checksum of every part:
+    ///
+    /// ```json
+    /// {
+    ///     "content_type": "multipart/form-data; boundary=boundary",
+    ///     "size": 136,
+    ///     "created": 1700000000,
+    ///     "parts": [
+    ///         { "name": "file", "filename": "a.txt", "content_type": "text/plain",
+    ///           "encoding": null, "size": 17, "checksum": "318f9c13" }
+    ///     ]
+    /// }
+    /// ```
+    ///
+    /// Sizes and checksums are recorded while the bodies are emitted for parts appended after
+    /// `with_manifest`. For other parts checksums are `null` and the sizes of streamed bodies
+    /// are only known if they declare a Content-Length.
+    pub fn finish_with_manifest(mut self) -> Result<(Bytes, String), MultipartError> {
+        self.close();
+
+        let mut data = BytesMut::new();
+        while let Some(chunk) = self.take_chunk()? {
+            data.extend_from_slice(&chunk);
+        }
+        let manifest = manifest::to_json(
+            &self.content_type(),
+            self.size + self.closing_len(),
+            self.clock.now(),
+            self.digest.algorithm(),
+            &self.summaries,
+            &self.records,
+        );
+        Ok((data.freeze(), manifest))
+    }
+
+    /// Append the closing delimiter, no parts may be appended afterwards
+    pub(crate) fn close(&mut self) {
+        if self.trailer.is_some() {
+            self.chunks
+                .push_back((Chunk::Data(Bytes::new()), ChunkRole::Trailer));
+        }
+
+        let crlf = if self.profile.final_crlf { "\r\n" } else { "" };
+        self.chunks.push_back((
+            Chunk::Data(Bytes::from(format!("--{}--{}", self.boundary, crlf))),
+            ChunkRole::Other,
+        ));
+    }
+
+    /// Encode the trailer part from the bodies emitted so far
+    fn encode_trailer(&mut self) -> Result<Bytes, MultipartError> {
+        // The trailer is counted in `size` from now on instead of being reserved
+        let name = self.trailer.take().unwrap_or_default();
+        let mut data = self.encode_trailer_head(&name)?;
+        data.put_slice(
+            manifest::trailer_json(
+                self.size,
+                self.digest.algorithm(),
+                &self.summaries,
+                &self.records,
+            )
+            .as_bytes(),
+        );
+        data.put_slice(b"\r\n");
+
+        self.size += data.len() as u64;
+        self.check_size(0, None)?;
+        Ok(data.freeze())
+    }
+
+    fn encode_trailer_head(&self, name: &str) -> Result<BytesMut, MultipartError> {
+        let headers = [
+            (
+                "Content-Disposition".to_string(),
+                content_disposition(&self.multipart_type, Some(name), None),
+            ),
+            ("Content-Type".to_string(), "application/json".to_string()),
+        ];
+        encode_head(&self.boundary, &headers, self.profile.strict_headers)
+    }
+
+    /// Room reserved for the trailer, which also lists `part` if given
+    fn trailer_reserve(&self, part: Option<&PartSummary>) -> Result<u64, MultipartError> {
+        let Some(name) = &self.trailer else {
+            return Ok(0);
+        };
+
+        let head = self.encode_trailer_head(name)?.len() as u64;
+        let parts_len = self.trailer_parts_len
+            + part.map_or(0, |part| manifest::part_len_bound(part, &*self.digest));
+        Ok(head + manifest::trailer_len_bound(self.digest.algorithm(), parts_len) + 2)
+    }
+
+    fn update_trailer_parts_len(&mut self) {
+        self.trailer_parts_len = self
+            .summaries
+            .iter()
+            .map(|summary| manifest::part_len_bound(summary, &*self.digest))
+            .sum();
+    }
+
+    /// Length of the closing delimiter
+    fn closing_len(&self) -> u64 {
+        self.boundary.len() as u64 + 4 + if self.profile.final_crlf { 2 } else { 0 }
+    }
+
+    /// Take the size of a streamed body from its record once it was emitted completely
+    fn finish_body(&mut self, role: ChunkRole) {
+        let ChunkRole::Body(part) = role else {
+            return;
+        };
+        if let (Some(Some(record)), Some(summary)) =
+            (self.records.get(part), self.summaries.get_mut(part))
+        {
+            summary.size = Some(record.size);
+        }
+    }
+
+    fn record_body(&mut self, role: ChunkRole, data: &[u8]) {
+        let ChunkRole::Body(part) = role else {
+            return;
+        };
+        if let Some(Some(record)) = self.records.get_mut(part) {
+            record.update(data);
+        }
+    }
+
+    fn push_part(
+        &mut self,
+        headers: &[(String, String)],
+        body: Chunk,
+    ) -> Result<(), MultipartError> {
+        let head = encode_head(&self.boundary, headers, self.profile.strict_headers)?;
+
+        let body = match (&self.codecs, body) {
+            (Some(codecs), Chunk::Data(data)) => Chunk::Data(codecs.encode(headers, data)?),
+            (Some(codecs), Chunk::Source(mut source)) if codecs.matches(headers) => {
+                let mut data = vec![];
+                source
+                    .read_to_end(&mut data)
+                    .map_err(|_| MultipartError::ReadingSourceFailed)?;
+                Chunk::Data(codecs.encode(headers, data.into())?)
+            }
+            (_, body) => body,
+        };
+
+        let size = match &body {
+            Chunk::Data(data) => Some(data.len() as u64),
+            Chunk::Source(_) => headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case("content-length"))
+                .and_then(|(_, value)| value.trim().parse().ok()),
+        };
+        let framing = head.len() as u64 + 2;
+        let summary = PartSummary::from_headers(headers, size);
+        self.check_size(framing + size.unwrap_or(0), Some(&summary))?;
+
+        if self.trailer.is_some() {
+            self.trailer_parts_len += manifest::part_len_bound(&summary, &*self.digest);
+        }
+        self.summaries.push(summary);
+        self.size += match &body {
+            Chunk::Data(data) => framing + data.len() as u64,
+            Chunk::Source(_) => framing,
+        };
+
+        let role = if self.record_bodies {
+            self.records
+                .push(Some(BodyRecord::new(self.digest.start())));
+            ChunkRole::Body(self.records.len() - 1)
+        } else {
+            self.records.push(None);
+            ChunkRole::Other
+        };
+        self.chunks
+            .push_back((Chunk::Data(head.freeze()), ChunkRole::Other));
+        self.chunks.push_back((body, role));
+        self.chunks
+            .push_back((Chunk::Data(Bytes::from_static(b"\r\n")), ChunkRole::Other));
+        Ok(())
+    }
+
+    /// Fail if `additional` more bytes would make the closed document exceed the limit, along
+    /// with the trailer listing the new `part`
+    fn check_size(
+        &self,
+        additional: u64,
+        part: Option<&PartSummary>,
+    ) -> Result<(), MultipartError> {
+        let Some(max_size) = self.max_size else {
+            return Ok(());
+        };
+
+        let closing = self.closing_len() + self.trailer_reserve(part)?;
+        if self.size + additional + closing > max_size {
+            return Err(MultipartError::DocumentTooLarge);
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn content_type(multipart_type: &MultipartType, boundary: &str) -> String {
+    // Boundaries containing tspecials must be quoted
+    let is_token = boundary
+        .bytes()
+        .all(|c| c.is_ascii_alphanumeric() || b"-_.".contains(&c));
+
+    if is_token {
+        format!("multipart/{}; boundary={}", multipart_type, boundary)
+    } else {
+        format!("multipart/{}; boundary=\"{}\"", multipart_type, boundary)
+    }
+}
+
+/// The Content-Disposition of a part, `form-data` in forms and `attachment` otherwise
+pub(crate) fn content_disposition(
+    multipart_type: &MultipartType,
+    name: Option<&str>,
+    filename: Option<&str>,
+) -> String {
+    let mut disposition = match multipart_type {
+        MultipartType::FormData => "form-data",
+        _ => "attachment",
+    }
+    .to_string();
+
+    if let Some(name) = name {
+        disposition.push_str(&format!("; name=\"{}\"", escape_param(name)));
+    }
+    if let Some(filename) = filename {
+        disposition.push_str(&format!("; filename=\"{}\"", escape_param(filename)));
+    }
+    disposition
+}
+
+/// Percent-encode the characters which could end a quoted parameter value or the header, as
+/// browsers do for form-data (RFC 7578, section 4.2)
+fn escape_param(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("%22"),
+            '\\' => escaped.push_str("%5C"),
+            '\r' => escaped.push_str("%0D"),
+            '\n' => escaped.push_str("%0A"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(feature = "mime_guess")]
+fn guess_content_type(path: &Path) -> String {
+    mime_guess::from_path(path)
+        .first_or_octet_stream()
+        .to_string()
+}
+
+#[cfg(not(feature = "mime_guess"))]
+fn guess_content_type(_path: &Path) -> String {
+    "application/octet-stream".to_string()
+}
+
+/// Encode the delimiter and the headers of a part
+pub(crate) fn encode_head(
+    boundary: &str,
+    headers: &[(String, String)],
+    strict_headers: bool,
+) -> Result<BytesMut, MultipartError> {
+    let mut head = BytesMut::new();
+    head.put_slice(b"--");
+    head.put_slice(boundary.as_bytes());
+    head.put_slice(b"\r\n");
+
+    for (key, value) in headers {
+        if !is_valid_header(key, value, strict_headers) {
+            return Err(MultipartError::InvalidItemHeader);
+        }
+        head.put_slice(key.as_bytes());
+        head.put_slice(b": ");
+        head.put_slice(value.as_bytes());
+        head.put_slice(b"\r\n");
+    }
+
+    head.put_slice(b"\r\n");
+    Ok(head)
+}
+
+fn is_valid_header(key: &str, value: &str, strict: bool) -> bool {
+    if strict {
+        return !key.is_empty()
+            && key
+                .bytes()
+                .all(|c| c.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&c))
+            && value
+                .bytes()
+                .all(|c| c.is_ascii_graphic() || c == b' ' || c == b'\t');
+    }
+
+    !key.is_empty()
+        && key.bytes().all(|c| c.is_ascii_graphic() && c != b':')
+        && !value.bytes().any(|c| c == b'\r' || c == b'\n')
+}
+
+/// Check the boundary against the grammar of RFC 2046
+pub(crate) fn validate_boundary(boundary: &str) -> Result<(), MultipartError> {
+    let valid_chars = boundary
+        .bytes()
+        .all(|c| c.is_ascii_alphanumeric() || b"'()+_,-./:=? ".contains(&c));
+
+    if boundary.is_empty() || boundary.len() > 70 || !valid_chars || boundary.ends_with(' ') {
+        return Err(MultipartError::InvalidBoundary);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use futures_util::StreamExt;
+
+    use super::*;
+    use crate::{clock::FixedClock, reader::MultipartReader};
+
+    #[futures_test::test]
+    async fn roundtrip() {
+        let mut writer = MultipartWriter::new(MultipartType::FormData);
+        writer
+            .append(
+                &[(
+                    "Content-Disposition".to_string(),
+                    "form-data; name=\"text\"".to_string(),
+                )],
+                "text default",
+            )
+            .unwrap();
+        writer
+            .append(
+                &[
+                    (
+                        "Content-Disposition".to_string(),
+                        "form-data; name=\"file1\"; filename=\"a.txt\"".to_string(),
+                    ),
+                    ("Content-Type".to_string(), "text/plain".to_string()),
+                ],
+                "Content of a.txt.\r\n",
+            )
+            .unwrap();
+
+        let headers = vec![("Content-Type".to_string(), writer.content_type())];
+        let data = writer.finish().unwrap();
+
+        let mut reader =
+            MultipartReader::<std::io::Error>::from_data_with_headers(&data, &headers).unwrap();
+        let first = reader.next().await.unwrap().unwrap();
+        let second = reader.next().await.unwrap().unwrap();
+        assert!(reader.next().await.is_none());
+        assert!(reader.finished_cleanly());
+
+        assert_eq!(&first.data[..], b"text default");
+        assert_eq!(second.header("content-type"), Some("text/plain"));
+        assert_eq!(&second.data[..], b"Content of a.txt.\r\n");
+    }
+
+    struct Pipe<R>(R);
+
+    impl<R: Read> Read for Pipe<R> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    impl<R> Seek for Pipe<R> {
+        fn seek(&mut self, _pos: SeekFrom) -> std::io::Result<u64> {
+            Err(ErrorKind::Unsupported.into())
+        }
+    }
+
+    #[test]
+    fn computes_deferred_headers() {
+        let body = vec![7u8; 3 * SOURCE_CHUNK_SIZE + 1];
+        let mut writer =
+            MultipartWriter::new_with_boundary("boundary", MultipartType::Mixed).unwrap();
+        writer
+            .append_seekable(
+                &[],
+                std::io::Cursor::new(body.clone()),
+                vec![Box::new(ContentLength::default())],
+            )
+            .unwrap();
+        writer
+            .append_seekable(
+                &[],
+                Pipe(&b"piped"[..]),
+                vec![Box::new(ContentLength::default())],
+            )
+            .unwrap();
+
+        let mut expected =
+            format!("--boundary\r\nContent-Length: {}\r\n\r\n", body.len()).into_bytes();
+        expected.extend_from_slice(&body);
+        expected.extend_from_slice(b"\r\n--boundary\r\n\r\npiped\r\n--boundary--\r\n");
+        assert_eq!(writer.finish().unwrap(), expected);
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        assert!(MultipartWriter::new_with_boundary("", MultipartType::Mixed).is_err());
+        assert!(MultipartWriter::new_with_boundary("trailing ", MultipartType::Mixed).is_err());
+
+        let mut writer = MultipartWriter::new_with_boundary("a b", MultipartType::Mixed).unwrap();
+        assert_eq!(writer.content_type(), "multipart/mixed; boundary=\"a b\"");
+        assert!(writer
+            .append(&[("X-Test".to_string(), "a\r\nb".to_string())], "")
+            .is_err());
+    }
+
+    #[test]
+    fn adds_files() {
+        let path = std::env::temp_dir().join(format!("multipart-rs-{}.txt", std::process::id()));
+        std::fs::write(&path, "Content of a.txt.").unwrap();
+
+        let mut writer =
+            MultipartWriter::new_with_boundary("boundary", MultipartType::FormData).unwrap();
+        writer.add_file("file1", &path, None).unwrap();
+        writer
+            .add_file("file2", &path, Some("text/x-custom"))
+            .unwrap();
+        assert!(writer.add_file("missing", "/nonexistent", None).is_err());
+
+        let guessed = if cfg!(feature = "mime_guess") {
+            "text/plain"
+        } else {
+            "application/octet-stream"
+        };
+        let types: Vec<_> = writer
+            .part_summaries()
+            .iter()
+            .map(|part| part.content_type.as_deref().unwrap())
+            .collect();
+        assert_eq!(types, vec![guessed, "text/x-custom"]);
+
+        let data = writer.finish().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let filename = path.file_name().unwrap().to_str().unwrap();
+        assert!(String::from_utf8_lossy(&data).contains(&format!(
+            "Content-Disposition: form-data; name=\"file1\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\nContent of a.txt.\r\n",
+            filename, guessed
+        )));
+    }
+
+    #[test]
+    fn appends_urlencoded_fields() {
+        let mut writer =
+            MultipartWriter::new_with_boundary("boundary", MultipartType::FormData).unwrap();
+        writer
+            .append_urlencoded_fields([("user", "alice"), ("lang", "en")])
+            .unwrap();
+        let data = writer.finish().unwrap();
+
+        assert_eq!(
+            data,
+            &b"--boundary\r
+Content-Disposition: form-data; name=\"user\"\r
+\r
+alice\r
+--boundary\r
+Content-Disposition: form-data; name=\"lang\"\r
+\r
+en\r
+--boundary--\r\n"[..]
+        );
+    }
+
+    #[test]
+    fn escapes_quotes_in_names() {
+        let mut writer =
+            MultipartWriter::new_with_boundary("boundary", MultipartType::FormData).unwrap();
+        writer
+            .append_urlencoded_fields([("x\"; filename=\"evil.sh", "1"), ("a\\b", "2")])
+            .unwrap();
+
+        let names: Vec<_> = writer
+            .part_summaries()
+            .iter()
+            .map(|part| (part.name.as_deref(), part.filename.as_deref()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                (Some("x%22; filename=%22evil.sh"), None),
+                (Some("a%5Cb"), None)
+            ]
+        );
+        assert!(String::from_utf8_lossy(&writer.finish().unwrap())
+            .contains("Content-Disposition: form-data; name=\"x%22; filename=%22evil.sh\"\r\n"));
+    }
+
+    #[futures_test::test]
+    async fn reads_back_values_with_colons() {
+        let mut writer =
+            MultipartWriter::new_with_boundary("boundary", MultipartType::FormData).unwrap();
+        let disposition = content_disposition(
+            &MultipartType::FormData,
+            Some("file"),
+            Some("notes: draft.txt"),
+        );
+        writer
+            .append(
+                &[
+                    ("Content-Disposition".to_string(), disposition),
+                    ("X-Time".to_string(), "12:30:00".to_string()),
+                ],
+                "draft",
+            )
+            .unwrap();
+        let data = writer.finish().unwrap();
+
+        let mut reader = MultipartReader::<std::io::Error>::from_data_with_boundary_and_type(
+            &data,
+            "boundary",
+            MultipartType::FormData,
+        )
+        .unwrap();
+        let item = reader.next().await.unwrap().unwrap();
+        assert_eq!(item.filename(), Some("notes: draft.txt"));
+        assert_eq!(item.header("x-time"), Some("12:30:00"));
+    }
+
+    #[test]
+    fn escapes_disposition_params() {
+        assert_eq!(
+            content_disposition(&MultipartType::Mixed, None, Some("a\"b\\c.txt")),
+            "attachment; filename=\"a%22b%5Cc.txt\""
+        );
+    }
+
+    #[test]
+    fn records_manifest() {
+        let mut writer = MultipartWriter::new_with_boundary("boundary", MultipartType::FormData)
+            .unwrap()
+            .with_manifest()
+            .with_clock(FixedClock(
+                SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            ));
+        writer
+            .append_urlencoded_fields([("text", "text default")])
+            .unwrap();
+        let headers = [
+            (
+                "Content-Disposition".to_string(),
+                "form-data; name=\"file\"; filename=\"a.txt\"".to_string(),
+            ),
+            ("Content-Type".to_string(), "text/plain".to_string()),
+        ];
+        writer
+            .append_reader(&headers, &b"Content of a.txt."[..])
+            .unwrap();
+        let first = writer.take_chunk().unwrap().unwrap();
+
+        let (rest, manifest) = writer.finish_with_manifest().unwrap();
+        assert_eq!(
+            manifest,
+            format!(
+                concat!(
+                    r#"{{"content_type":"multipart/form-data; boundary=boundary","size":{},"created":1700000000,"checksum_algorithm":"crc32","parts":["#,
+                    r#"{{"name":"text","filename":null,"content_type":null,"encoding":null,"size":12,"checksum":"b28facdc"}},"#,
+                    r#"{{"name":"file","filename":"a.txt","content_type":"text/plain","encoding":null,"size":17,"checksum":"318f9c13"}}]}}"#
+                ),
+                first.len() + rest.len()
+            )
+        );
+    }
+
+    #[test]
+    fn records_only_parts_appended_afterwards() {
+        let mut writer =
+            MultipartWriter::new_with_boundary("boundary", MultipartType::Mixed).unwrap();
+        writer.append(&[], "first").unwrap();
+        let mut writer = writer.with_manifest();
+        writer.append(&[], "second").unwrap();
+
+        let (_, manifest) = writer.finish_with_manifest().unwrap();
+        assert!(manifest.contains(r#""size":5,"checksum":null}"#));
+        assert!(manifest.contains(r#""size":6,"checksum":"b61f1169"}"#));
+    }
+
+    #[test]
+    fn records_with_custom_digest() {
+        /// Sum of all bytes, enough to tell which digest was used
+        struct Sum(u16);
+
+        impl BodyDigest for Sum {
+            fn algorithm(&self) -> &str {
+                "sum"
+            }
+
+            fn start(&self) -> Box<dyn BodyDigest> {
+                Box::new(Sum(0))
+            }
+
+            fn update(&mut self, data: &[u8]) {
+                for byte in data {
+                    self.0 = self.0.wrapping_add(*byte as u16);
+                }
+            }
+
+            fn finish(&self) -> Vec<u8> {
+                self.0.to_be_bytes().to_vec()
+            }
+        }
+
+        let mut writer = MultipartWriter::new_with_boundary("boundary", MultipartType::Mixed)
+            .unwrap()
+            .with_digest(Sum(0))
+            .with_manifest();
+        writer.append(&[], "ab").unwrap();
+
+        let (_, manifest) = writer.finish_with_manifest().unwrap();
+        assert!(manifest.contains(r#""checksum_algorithm":"sum","#));
+        assert!(manifest.contains(r#""size":2,"checksum":"00c3"}"#));
+    }
+
+    #[futures_test::test]
+    async fn appends_trailer() {
+        let mut writer = MultipartWriter::new_with_boundary("boundary", MultipartType::FormData)
+            .unwrap()
+            .with_trailer("trailer");
+        let headers = [
+            (
+                "Content-Disposition".to_string(),
+                "form-data; name=\"file\"; filename=\"a.txt\"".to_string(),
+            ),
+            ("Content-Type".to_string(), "text/plain".to_string()),
+        ];
+        writer
+            .append_reader(&headers, &b"Content of a.txt."[..])
+            .unwrap();
+        let data = writer.finish().unwrap();
+
+        let items: Vec<_> = MultipartReader::<std::io::Error>::from_data_with_boundary_and_type(
+            &data,
+            "boundary",
+            MultipartType::FormData,
+        )
+        .unwrap()
+        .map(|item| item.unwrap())
+        .collect()
+        .await;
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[1].name(), Some("trailer"));
+        assert_eq!(items[1].header("content-type"), Some("application/json"));
+        assert_eq!(
+            &items[1].data[..],
+            concat!(
+                r#"{"count":1,"size":122,"checksum_algorithm":"crc32","parts":["#,
+                r#"{"name":"file","filename":"a.txt","content_type":"text/plain","encoding":null,"size":17,"checksum":"318f9c13"}]}"#
+            )
+            .as_bytes()
+        );
+    }
+
+    #[test]
+    fn fails_early_when_too_large() {
+        // Each part takes 20 bytes, the closing delimiter 14
+        let mut writer = MultipartWriter::new_with_boundary("boundary", MultipartType::Mixed)
+            .unwrap()
+            .with_max_size(54);
+        writer.append(&[], "1234").unwrap();
+        writer.append(&[], "1234").unwrap();
+        assert!(matches!(
+            writer.append(&[], "1"),
+            Err(MultipartError::DocumentTooLarge)
+        ));
+        assert_eq!(writer.finish().unwrap().len(), 54);
+
+        let mut writer = MultipartWriter::new_with_boundary("boundary", MultipartType::Mixed)
+            .unwrap()
+            .with_max_size(50);
+        writer.append_reader(&[], &[0u8; 64][..]).unwrap();
+        assert!(matches!(
+            writer.finish(),
+            Err(MultipartError::DocumentTooLarge)
+        ));
+
+        // Room for the trailer is reserved by every append
+        let mut writer = MultipartWriter::new_with_boundary("boundary", MultipartType::Mixed)
+            .unwrap()
+            .with_trailer("trailer")
+            .with_max_size(350);
+        writer.append(&[], "1234").unwrap();
+        assert!(matches!(
+            writer.append(&[], "1234"),
+            Err(MultipartError::DocumentTooLarge)
+        ));
+        assert!(writer.finish().unwrap().len() <= 350);
+    }
+
+    #[futures_test::test]
+    async fn applies_output_profile() {
+        let headers = [("X-Test".to_string(), "caf\u{e9}".to_string())];
+
+        let mut writer = MultipartWriter::new_with_boundary("boundary", MultipartType::Mixed)
+            .unwrap()
+            .with_output_profile(OutputProfile {
+                final_crlf: false,
+                quote_boundary: true,
+                ..OutputProfile::DEFAULT
+            });
+        writer.append(&headers, "data").unwrap();
+        assert_eq!(
+            writer.content_type(),
+            "multipart/mixed; boundary=\"boundary\""
+        );
+
+        let data = writer.finish().unwrap();
+        assert!(data.ends_with(b"data\r\n--boundary--"));
+        let mut reader = MultipartReader::<std::io::Error>::from_data_with_boundary_and_type(
+            &data,
+            "boundary",
+            MultipartType::Mixed,
+        )
+        .unwrap();
+        assert!(reader.next().await.unwrap().is_ok());
+        assert!(reader.next().await.is_none());
+        assert!(reader.finished_cleanly());
+
+        let mut writer = MultipartWriter::new_with_boundary("boundary", MultipartType::Mixed)
+            .unwrap()
+            .with_output_profile(OutputProfile::STRICT);
+        assert!(writer.append(&headers, "data").is_err());
+    }
+}